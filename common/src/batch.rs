@@ -0,0 +1,235 @@
+// Hashing of batches - must stay in sync with the Executor facet.
+
+use std::{collections::HashMap, error::Error};
+
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, B256, U256, address, keccak256},
+    providers::Provider,
+};
+
+use crate::{
+    CommitBatchInfo, CommitBoojumOSBatchInfo, IHyperchain::IHyperchainInstance, StoredBatchInfo,
+};
+
+/// Protocol upgrade transaction, that is included in the outputs hash of the batch that executed it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UpgradeTx {
+    pub tx_hash: B256,
+    /// Batch in which the upgrade was included (0 if it was not committed yet).
+    pub batch_number: u64,
+}
+
+impl UpgradeTx {
+    /// Fetches the currently pending upgrade (both values are cleared once the upgrade batch is executed).
+    pub async fn fetch<P: Provider>(
+        contract: &IHyperchainInstance<P>,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            tx_hash: contract.getL2SystemContractsUpgradeTxHash().call().await?,
+            batch_number: contract
+                .getL2SystemContractsUpgradeBatchNumber()
+                .call()
+                .await?
+                .try_into()?,
+        })
+    }
+
+    /// Same, as it was at a given L1 block (needs an archive node for older blocks).
+    pub async fn fetch_at<P: Provider>(
+        contract: &IHyperchainInstance<P>,
+        block_number: u64,
+    ) -> Result<Self, Box<dyn Error>> {
+        let block = BlockId::number(block_number);
+        Ok(Self {
+            tx_hash: contract
+                .getL2SystemContractsUpgradeTxHash()
+                .block(block)
+                .call()
+                .await?,
+            batch_number: contract
+                .getL2SystemContractsUpgradeBatchNumber()
+                .block(block)
+                .call()
+                .await?
+                .try_into()?,
+        })
+    }
+
+    /// Upgrade tx hash to use in the outputs hash of a given batch.
+    pub fn hash_for_batch(&self, batch_number: u64) -> B256 {
+        if self.batch_number != 0 && self.batch_number == batch_number {
+            self.tx_hash
+        } else {
+            B256::ZERO
+        }
+    }
+}
+
+/// Computes the batch output hash (this is what ends up in `StoredBatchInfo.commitment`).
+/// `upgrade_tx_hash` is zero for all the batches, except for the one that included the protocol upgrade.
+pub fn compute_batch_outputs_hash(batch: &CommitBoojumOSBatchInfo, upgrade_tx_hash: B256) -> B256 {
+    let mut bytes = Vec::with_capacity(32 + 8 + 8 + 20 + 32 + 32 + 32 + 32 + 32);
+
+    // Encode chainId as 32-byte big-endian.
+    bytes.extend_from_slice(&batch.chainId.to_be_bytes::<32>());
+    // Encode firstBlockTimestamp (uint64 - 8 bytes)
+    bytes.extend_from_slice(&batch.firstBlockTimestamp.to_be_bytes());
+    // Encode lastBlockTimestamp (uint64 - 8 bytes)
+    bytes.extend_from_slice(&batch.lastBlockTimestamp.to_be_bytes());
+    // Encode l2DaValidator as 20 bytes already.
+    bytes.extend_from_slice(batch.l2DaValidator.as_slice());
+    // Encode daCommitment (bytes32 - 32 bytes)
+    bytes.extend_from_slice(batch.daCommitment.as_slice());
+    // Encode numberOfLayer1Txs as 32-byte big-endian.
+    bytes.extend_from_slice(&batch.numberOfLayer1Txs.to_be_bytes::<32>());
+    // Encode priorityOperationsHash (bytes32 - 32 bytes)
+    bytes.extend_from_slice(batch.priorityOperationsHash.as_slice());
+    // Encode l2LogsTreeRoot (bytes32 - 32 bytes)
+    bytes.extend_from_slice(batch.l2LogsTreeRoot.as_slice());
+    // Encode upgrade tx hash (bytes32 - 32 bytes)
+    bytes.extend_from_slice(upgrade_tx_hash.as_slice());
+
+    // Compute and return the keccak256 hash.
+    keccak256(&bytes)
+}
+
+/// Converts the commit info into the form that the contract stores (and expects in prove & execute).
+pub fn commit_to_stored(info: CommitBoojumOSBatchInfo, upgrade_tx_hash: B256) -> StoredBatchInfo {
+    StoredBatchInfo {
+        batchNumber: info.batchNumber,
+        batchHash: info.newStateCommitment,
+        indexRepeatedStorageChanges: 0,
+        numberOfLayer1Txs: info.numberOfLayer1Txs,
+        priorityOperationsHash: info.priorityOperationsHash,
+        l2LogsTreeRoot: info.l2LogsTreeRoot,
+        timestamp: U256::from(0), // For Boojum OS not used, 0
+        commitment: compute_batch_outputs_hash(&info, upgrade_tx_hash), // For Boojum OS batches we'll store batch output hash here
+    }
+}
+
+/// L1 messenger - the only sender of the system log with the L2 to L1 logs tree root.
+const L2_TO_L1_MESSENGER: Address = address!("0000000000000000000000000000000000008008");
+const L2_TO_L1_LOGS_TREE_ROOT_KEY: u64 = 0;
+const SYSTEM_LOG_SIZE: usize = 88;
+
+/// Same as `commit_to_stored`, but for Era batches.
+/// Their commitment depends on the data that is not in the calldata, so it must come from the `BlockCommit` event.
+pub fn era_commit_to_stored(
+    info: CommitBatchInfo,
+    commitment: B256,
+) -> Result<StoredBatchInfo, Box<dyn Error>> {
+    let l2_logs_tree_root = era_l2_logs_tree_root(&info.systemLogs).ok_or_else(|| {
+        format!(
+            "Batch {} has no L2 to L1 logs tree root in its system logs",
+            info.batchNumber
+        )
+    })?;
+    Ok(StoredBatchInfo {
+        batchNumber: info.batchNumber,
+        batchHash: info.newStateRoot,
+        indexRepeatedStorageChanges: info.indexRepeatedStorageChanges,
+        numberOfLayer1Txs: info.numberOfLayer1Txs,
+        priorityOperationsHash: info.priorityOperationsHash,
+        l2LogsTreeRoot: l2_logs_tree_root,
+        timestamp: U256::from(info.timestamp),
+        commitment,
+    })
+}
+
+/// Finds the L2 to L1 logs tree root in the system logs (each log is 88 bytes: 4 bytes of shard id,
+/// isService and tx number, then sender, key and value).
+fn era_l2_logs_tree_root(system_logs: &[u8]) -> Option<B256> {
+    system_logs
+        .chunks_exact(SYSTEM_LOG_SIZE)
+        .find(|log| {
+            Address::from_slice(&log[4..24]) == L2_TO_L1_MESSENGER
+                && B256::from_slice(&log[24..56])
+                    == B256::from(U256::from(L2_TO_L1_LOGS_TREE_ROOT_KEY))
+        })
+        .map(|log| B256::from_slice(&log[56..88]))
+}
+
+/// FRI public input for a single batch.
+pub fn get_batch_public_input(prev_batch: &StoredBatchInfo, batch: &StoredBatchInfo) -> B256 {
+    let mut bytes = Vec::with_capacity(32 * 3);
+    bytes.extend_from_slice(prev_batch.batchHash.as_slice());
+    bytes.extend_from_slice(batch.batchHash.as_slice());
+    bytes.extend_from_slice(batch.commitment.as_slice());
+    keccak256(&bytes)
+}
+
+/// Public input of the Era (FFLONK or PLONK wrapper) proof of a single batch - `_getBatchProofPublicInput` in the
+/// Executor facet: keccak of the previous and the current commitment, shifted right by 32 bits.
+pub fn get_era_snark_public_input(prev_batch: &StoredBatchInfo, batch: &StoredBatchInfo) -> B256 {
+    let mut bytes = Vec::with_capacity(32 * 2);
+    bytes.extend_from_slice(prev_batch.commitment.as_slice());
+    bytes.extend_from_slice(batch.commitment.as_slice());
+    shift_b256_right(&keccak256(&bytes))
+}
+
+pub fn shift_b256_right(input: &B256) -> B256 {
+    let mut bytes = [0_u8; 32];
+    bytes[4..32].copy_from_slice(&input.as_slice()[0..28]);
+    B256::from_slice(&bytes)
+}
+
+/// Checks that the range is valid (both ends inclusive, starting after the genesis batch), and that we have
+/// all its batches - and the one before it.
+pub fn check_batch_range(
+    batches: &HashMap<u64, StoredBatchInfo>,
+    start: u64,
+    end: u64,
+) -> Result<(), Box<dyn Error>> {
+    if start == 0 || start > end {
+        return Err(format!("Invalid batch range {}-{}", start, end).into());
+    }
+    if let Some(missing) = (start - 1..=end).find(|i| !batches.contains_key(i)) {
+        return Err(format!(
+            "Batch {} not found (needed for range {}-{})",
+            missing, start, end
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Same as `get_era_snark_public_input`, but checks that we have the batch (and the one before it).
+pub fn era_snark_public_input_for_batch(
+    batches: &HashMap<u64, StoredBatchInfo>,
+    batch_number: u64,
+) -> Result<B256, Box<dyn Error>> {
+    check_batch_range(batches, batch_number, batch_number)?;
+    Ok(get_era_snark_public_input(
+        &batches[&(batch_number - 1)],
+        &batches[&batch_number],
+    ))
+}
+
+/// SNARK public input for a range of batches (both ends inclusive).
+pub fn snark_public_input_for_range(
+    batches: &HashMap<u64, StoredBatchInfo>,
+    start: u64,
+    end: u64,
+) -> Result<B256, Box<dyn Error>> {
+    check_batch_range(batches, start, end)?;
+    let mut result = B256::ZERO;
+    for i in start..=end {
+        let batch = &batches[&i];
+        let prev_batch = &batches[&(i - 1)];
+        let public_input = get_batch_public_input(prev_batch, batch);
+        // Snark public input is public_input >> 32.
+        let snark_input = shift_b256_right(&public_input);
+
+        if i == start {
+            result = snark_input;
+        } else {
+            // Combine with previous result.
+            let mut combined = [0_u8; 64];
+            combined[..32].copy_from_slice(&result.0);
+            combined[32..].copy_from_slice(&snark_input.0);
+            result = shift_b256_right(&keccak256(combined));
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,88 @@
+// Finding the diamond proxy of a chain - through the bridgehub on L1 (or the L2 node, which knows both).
+
+use std::error::Error;
+
+use alloy::{
+    hex::FromHex,
+    primitives::{Address, U256},
+    providers::{Provider, ProviderBuilder},
+    sol,
+};
+
+sol! {
+    #[sol(rpc)]
+    contract IBridgehub {
+        function getZKChain(uint256 _chainId) external view returns (address);
+    }
+}
+
+/// Returns the bridgehub address - from the L2 node ('zks_getBridgehubContract').
+pub async fn get_bridgehub(l2_rpc: &str) -> Result<Address, Box<dyn Error>> {
+    let provider = ProviderBuilder::new().connect(l2_rpc).await?;
+    let bridgehub = provider
+        .raw_request::<_, Address>("zks_getBridgehubContract".into(), ())
+        .await
+        .map_err(|err| format!("Failed to get bridgehub address from {}: {}", l2_rpc, err))?;
+    Ok(bridgehub)
+}
+
+/// Returns the chain id of the L2 node.
+pub async fn get_chain_id(l2_rpc: &str) -> Result<u64, Box<dyn Error>> {
+    let provider = ProviderBuilder::new().connect(l2_rpc).await?;
+    Ok(provider.get_chain_id().await?)
+}
+
+/// Returns the diamond proxy of a given chain (fails if the chain is not registered in the bridgehub).
+pub async fn get_diamond_proxy<P: Provider>(
+    l1_provider: &P,
+    bridgehub: Address,
+    chain_id: u64,
+) -> Result<Address, Box<dyn Error>> {
+    let diamond_proxy = IBridgehub::new(bridgehub, l1_provider)
+        .getZKChain(U256::from(chain_id))
+        .call()
+        .await?;
+    if diamond_proxy == Address::ZERO {
+        return Err(format!(
+            "Chain {} is not registered in bridgehub {}",
+            chain_id, bridgehub
+        )
+        .into());
+    }
+    Ok(diamond_proxy)
+}
+
+/// Picks the diamond proxy from whatever was given: its address, the bridgehub and chain id,
+/// or the L2 node (which gives the bridgehub and chain id, unless they are given explicitly).
+pub async fn resolve_diamond_proxy<P: Provider>(
+    l1_provider: &P,
+    address: Option<&str>,
+    bridgehub: Option<&str>,
+    chain_id: Option<u64>,
+    l2_rpc: Option<&str>,
+) -> Result<Address, Box<dyn Error>> {
+    if let Some(address) = address {
+        return Ok(Address::from_hex(address)?);
+    }
+    let bridgehub = match (bridgehub, l2_rpc) {
+        (Some(bridgehub), _) => Address::from_hex(bridgehub)?,
+        (None, Some(l2_rpc)) => get_bridgehub(l2_rpc).await?,
+        (None, None) => {
+            return Err(
+                "Specify the diamond proxy: --address, --bridgehub with --chain-id, or --l2-rpc"
+                    .into(),
+            );
+        }
+    };
+    let chain_id = match (chain_id, l2_rpc) {
+        (Some(chain_id), _) => chain_id,
+        (None, Some(l2_rpc)) => get_chain_id(l2_rpc).await?,
+        (None, None) => return Err("--bridgehub needs --chain-id (or --l2-rpc)".into()),
+    };
+    let diamond_proxy = get_diamond_proxy(l1_provider, bridgehub, chain_id).await?;
+    eprintln!(
+        "Diamond proxy of chain {} (bridgehub {}): {}",
+        chain_id, bridgehub, diamond_proxy
+    );
+    Ok(diamond_proxy)
+}
@@ -0,0 +1,184 @@
+// Decoding of the `commitData` argument of `commitBatchesSharedBridge`.
+// The first byte is the encoding version, and the rest depends on it (and on the protocol version):
+// Era chains send `CommitBatchInfo`, Boojum OS chains send `CommitBoojumOSBatchInfo`.
+
+use std::{error::Error, fmt};
+
+use alloy::{eips::BlockId, providers::Provider, sol_types::SolCall};
+use serde::Serialize;
+
+use crate::{
+    CommitBatchInfo, CommitBoojumOSBatchInfo,
+    IHyperchain::{self, IHyperchainInstance},
+    StoredBatchInfo,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProtocolVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ProtocolVersion {
+    pub async fn fetch<P: Provider>(
+        contract: &IHyperchainInstance<P>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let semver = contract.getSemverProtocolVersion().call().await?;
+        Ok(Self {
+            major: semver._0,
+            minor: semver._1,
+            patch: semver._2,
+        })
+    }
+
+    /// Protocol version at a given L1 block (needs an archive node for older blocks).
+    pub async fn fetch_at<P: Provider>(
+        contract: &IHyperchainInstance<P>,
+        block_number: u64,
+    ) -> Result<Self, Box<dyn Error>> {
+        let semver = contract
+            .getSemverProtocolVersion()
+            .block(BlockId::number(block_number))
+            .call()
+            .await?;
+        Ok(Self {
+            major: semver._0,
+            minor: semver._1,
+            patch: semver._2,
+        })
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitDataLayout {
+    /// `StoredBatchInfo` + `CommitBoojumOSBatchInfo[]`
+    BoojumOs,
+    /// `StoredBatchInfo` + `CommitBatchInfo[]` (same as `operator/calldata_utils.py`).
+    Era,
+}
+
+struct CommitDataDecoder {
+    encoding_version: u8,
+    /// Protocol versions that use this encoding - from `min_protocol_version`,
+    /// up to (but not including) `max_protocol_version`.
+    min_protocol_version: ProtocolVersion,
+    max_protocol_version: Option<ProtocolVersion>,
+    layout: CommitDataLayout,
+}
+
+impl CommitDataDecoder {
+    fn matches(&self, encoding_version: u8, protocol_version: ProtocolVersion) -> bool {
+        self.encoding_version == encoding_version
+            && self.min_protocol_version <= protocol_version
+            && self
+                .max_protocol_version
+                .is_none_or(|max| protocol_version < max)
+    }
+}
+
+const fn version(minor: u32) -> ProtocolVersion {
+    ProtocolVersion {
+        major: 0,
+        minor,
+        patch: 0,
+    }
+}
+
+/// Known encodings - at most one matches the version byte and the protocol version
+/// (the protocol version of the commit, not the current one - the layout may change on an upgrade).
+const DECODERS: &[CommitDataDecoder] = &[
+    CommitDataDecoder {
+        encoding_version: 0,
+        min_protocol_version: version(26),
+        max_protocol_version: Some(version(28)),
+        layout: CommitDataLayout::Era,
+    },
+    CommitDataDecoder {
+        encoding_version: 0,
+        min_protocol_version: version(28),
+        max_protocol_version: None,
+        layout: CommitDataLayout::BoojumOs,
+    },
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "layout")]
+pub enum DecodedCommitData {
+    BoojumOs {
+        stored: StoredBatchInfo,
+        commits: Vec<CommitBoojumOSBatchInfo>,
+    },
+    Era {
+        stored: StoredBatchInfo,
+        commits: Vec<CommitBatchInfo>,
+    },
+}
+
+impl DecodedCommitData {
+    /// Info about the last batch before the committed ones.
+    pub fn stored(&self) -> &StoredBatchInfo {
+        match self {
+            DecodedCommitData::BoojumOs { stored, .. } => stored,
+            DecodedCommitData::Era { stored, .. } => stored,
+        }
+    }
+}
+
+/// Decodes the `commitData` argument of `commitBatchesSharedBridge`.
+pub fn decode_commit_data(
+    commit_data: &[u8],
+    protocol_version: ProtocolVersion,
+) -> Result<DecodedCommitData, Box<dyn Error>> {
+    let (&encoding_version, data) = commit_data.split_first().ok_or("Empty commit data")?;
+
+    let decoder = DECODERS
+        .iter()
+        .find(|decoder| decoder.matches(encoding_version, protocol_version))
+        .ok_or_else(|| {
+            format!(
+                "Unsupported commit data encoding version {} for protocol version {}",
+                encoding_version, protocol_version
+            )
+        })?;
+    decode_with_layout(decoder.layout, data).ok_or_else(|| {
+        format!(
+            "Commit data (encoding version {}, protocol version {}) doesn't match the {:?} layout",
+            encoding_version, protocol_version, decoder.layout
+        )
+        .into()
+    })
+}
+
+/// Returns the decoded data only if it is the canonical encoding (re-encoding gives the same bytes) -
+/// anything else means that the data is not what the layout expects.
+fn decode_with_layout(layout: CommitDataLayout, data: &[u8]) -> Option<DecodedCommitData> {
+    match layout {
+        CommitDataLayout::BoojumOs => {
+            let decoded = IHyperchain::commitDataPiecesCall::abi_decode_raw(data).ok()?;
+            is_canonical(&decoded, data).then_some(DecodedCommitData::BoojumOs {
+                stored: decoded.stored,
+                commits: decoded.commits,
+            })
+        }
+        CommitDataLayout::Era => {
+            let decoded = IHyperchain::commitDataPiecesEraCall::abi_decode_raw(data).ok()?;
+            is_canonical(&decoded, data).then_some(DecodedCommitData::Era {
+                stored: decoded.stored,
+                commits: decoded.commits,
+            })
+        }
+    }
+}
+
+fn is_canonical<C: SolCall>(decoded: &C, data: &[u8]) -> bool {
+    let mut encoded = Vec::with_capacity(data.len());
+    decoded.abi_encode_raw(&mut encoded);
+    encoded == data
+}
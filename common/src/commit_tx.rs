@@ -0,0 +1,112 @@
+// Recovering the commit data from L1 transactions.
+// Commits don't have to be sent directly to the diamond proxy - on production chains they go through
+// ValidatorTimelock (or some multisig), so we might have to look at the internal calls.
+
+use std::error::Error;
+
+use alloy::{
+    primitives::{Address, B256, Bytes},
+    providers::{Provider, ext::DebugApi},
+    rpc::types::trace::geth::{CallConfig, CallFrame, GethDebugTracingOptions},
+    sol,
+    sol_types::SolCall,
+};
+
+use crate::IHyperchain;
+
+sol! {
+    // Newer ValidatorTimelock versions take the chain address instead of the chain id.
+    // (older ones have the same signature as the diamond proxy).
+    contract IValidatorTimelock {
+        function commitBatchesSharedBridge(
+            address _chainAddress,
+            uint256 _processFrom,
+            uint256 _processTo,
+            bytes calldata _commitData
+        );
+
+        function proveBatchesSharedBridge(
+            address _chainAddress,
+            uint256 _processBatchFrom,
+            uint256 _processBatchTo,
+            bytes calldata _proofData
+        );
+
+        function executeBatchesSharedBridge(
+            address _chainAddress,
+            uint256 _processFrom,
+            uint256 _processTo,
+            bytes calldata _executeData
+        );
+    }
+}
+
+/// Returns the `commitData` of all the commit calls to the diamond proxy made by a given transaction.
+pub async fn find_commit_data<P: Provider>(
+    provider: &P,
+    tx_hash: B256,
+    input: &Bytes,
+    diamond_proxy: Address,
+) -> Result<Vec<Bytes>, Box<dyn Error>> {
+    if input.starts_with(&IHyperchain::commitBatchesSharedBridgeCall::SELECTOR) {
+        let decoded = IHyperchain::commitBatchesSharedBridgeCall::abi_decode(input)?;
+        return Ok(vec![decoded.commitData]);
+    }
+
+    // Commit was done by some other contract - look for the inner call in the trace.
+    let options = GethDebugTracingOptions::call_tracer(CallConfig::default());
+    match provider.debug_trace_transaction(tx_hash, options).await {
+        Ok(trace) => {
+            let frame = trace.try_into_call_frame()?;
+            let mut result = vec![];
+            collect_commit_calls(&frame, diamond_proxy, &mut result)?;
+            Ok(result)
+        }
+        Err(err) => {
+            eprintln!(
+                "Failed to trace transaction {} ({}) - trying known wrappers",
+                tx_hash, err
+            );
+            Ok(unwrap_known_wrappers(input, diamond_proxy)?
+                .into_iter()
+                .collect())
+        }
+    }
+}
+
+fn collect_commit_calls(
+    frame: &CallFrame,
+    diamond_proxy: Address,
+    result: &mut Vec<Bytes>,
+) -> Result<(), Box<dyn Error>> {
+    // Reverted calls (and everything they did) don't count.
+    if frame.error.is_some() {
+        return Ok(());
+    }
+    if frame.to == Some(diamond_proxy)
+        && frame
+            .input
+            .starts_with(&IHyperchain::commitBatchesSharedBridgeCall::SELECTOR)
+    {
+        let decoded = IHyperchain::commitBatchesSharedBridgeCall::abi_decode(&frame.input)?;
+        result.push(decoded.commitData);
+    }
+    for call in &frame.calls {
+        collect_commit_calls(call, diamond_proxy, result)?;
+    }
+    Ok(())
+}
+
+/// Fallback for nodes that don't support tracing.
+fn unwrap_known_wrappers(
+    input: &Bytes,
+    diamond_proxy: Address,
+) -> Result<Option<Bytes>, Box<dyn Error>> {
+    if input.starts_with(&IValidatorTimelock::commitBatchesSharedBridgeCall::SELECTOR) {
+        let decoded = IValidatorTimelock::commitBatchesSharedBridgeCall::abi_decode(input)?;
+        if decoded._chainAddress == diamond_proxy {
+            return Ok(Some(decoded._commitData));
+        }
+    }
+    Ok(None)
+}
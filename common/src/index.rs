@@ -0,0 +1,468 @@
+// On-disk index of the committed batches, so that we don't have to rescan L1 on every run.
+
+use std::{
+    collections::{BTreeMap, HashMap, hash_map::Entry},
+    error::Error,
+    fs,
+    path::PathBuf,
+};
+
+use alloy::{
+    consensus::Transaction,
+    primitives::{Address, B256},
+    providers::Provider,
+    rpc::types::Filter,
+    sol_types::SolEvent,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CommitBatchInfo, CommitBoojumOSBatchInfo, DecodedCommitData, IHyperchain, ProtocolVersion,
+    StoredBatchInfo, UpgradeTx, commit_to_stored, commit_tx::find_commit_data, decode_commit_data,
+    era_commit_to_stored,
+};
+
+pub const DEFAULT_INDEX_DIR: &str = ".batch_index";
+
+/// Bump whenever the format of the file changes - older files are then ignored (and rebuilt).
+const INDEX_VERSION: u32 = 4;
+
+/// How many L1 blocks we look at when building the index from scratch (if the batches that we need are
+/// older, we keep scanning back).
+const INITIAL_SCAN_BLOCKS: u64 = 1_000_000;
+const SCAN_CHUNK_SIZE: u64 = 10_000;
+
+/// Batches committed to a given diamond proxy (on a given L1 chain).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchIndex {
+    pub version: u32,
+    pub l1_chain_id: u64,
+    pub diamond_proxy: Address,
+    /// Last L1 block that was already scanned for `BlockCommit` and `BlocksRevert` events.
+    pub last_scanned_block: Option<u64>,
+    /// First L1 block that was scanned (everything from here to `last_scanned_block` is in the index).
+    pub first_scanned_block: Option<u64>,
+    /// `totalBatchesCommitted` of the `BlocksRevert` events in the scanned blocks, by L1 block
+    /// (so that the data derived from the batches can be reverted too).
+    pub reverts: BTreeMap<u64, u64>,
+    /// Only for Boojum OS chains (for Era chains there is just the `stored` info).
+    pub commits: BTreeMap<u64, CommitBoojumOSBatchInfo>,
+    pub stored: BTreeMap<u64, StoredBatchInfo>,
+    /// Blob versioned hashes of the commit transaction (only for batches committed with blobs).
+    pub blob_hashes: BTreeMap<u64, Vec<B256>>,
+}
+
+impl BatchIndex {
+    pub fn new(l1_chain_id: u64, diamond_proxy: Address) -> Self {
+        Self {
+            version: INDEX_VERSION,
+            l1_chain_id,
+            diamond_proxy,
+            last_scanned_block: None,
+            first_scanned_block: None,
+            reverts: Default::default(),
+            commits: Default::default(),
+            stored: Default::default(),
+            blob_hashes: Default::default(),
+        }
+    }
+
+    fn path(index_dir: &str, l1_chain_id: u64, diamond_proxy: Address) -> PathBuf {
+        PathBuf::from(index_dir).join(format!("{}_{}.json", l1_chain_id, diamond_proxy))
+    }
+
+    /// Loads the index from disk - or returns an empty one if there is none (or it is outdated).
+    pub fn load(index_dir: &str, l1_chain_id: u64, diamond_proxy: Address) -> Self {
+        let path = Self::path(index_dir, l1_chain_id, diamond_proxy);
+        let index = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<BatchIndex>(&content).ok());
+        match index {
+            Some(index) if index.version == INDEX_VERSION => index,
+            Some(_) => {
+                eprintln!("Ignoring outdated batch index {}", path.display());
+                Self::new(l1_chain_id, diamond_proxy)
+            }
+            None => Self::new(l1_chain_id, diamond_proxy),
+        }
+    }
+
+    pub fn save(&self, index_dir: &str) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(index_dir)?;
+        let path = Self::path(index_dir, self.l1_chain_id, self.diamond_proxy);
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Fetches the `BlockCommit` and `BlocksRevert` events that happened since the last scan,
+    /// and applies them in the order in which they happened on L1.
+    /// Then scans back until `first_batch` is in the index - by default the last executed batch
+    /// (if there is anything to prove or execute), as every prove & execute starts from it.
+    pub async fn sync<P: Provider>(
+        &mut self,
+        provider: &P,
+        first_batch: Option<u64>,
+    ) -> Result<(), Box<dyn Error>> {
+        let latest_block = provider.get_block_number().await?;
+        let start_block = match self.last_scanned_block {
+            Some(last) => last + 1,
+            None => latest_block.saturating_sub(INITIAL_SCAN_BLOCKS),
+        };
+
+        eprintln!(
+            "Scanning blocks from {} to {}...",
+            start_block, latest_block
+        );
+
+        let contract = IHyperchain::new(self.diamond_proxy, provider);
+        let total_committed: u64 = contract
+            .getTotalBatchesCommitted()
+            .call()
+            .await?
+            .try_into()?;
+        let scan = ScanContext {
+            upgrade: UpgradeTx::fetch(&contract).await?,
+            total_executed: contract
+                .getTotalBatchesExecuted()
+                .call()
+                .await?
+                .try_into()?,
+        };
+
+        let mut from_block = start_block;
+        while from_block <= latest_block {
+            let to_block = (from_block + SCAN_CHUNK_SIZE - 1).min(latest_block);
+            self.scan_chunk(provider, &scan, from_block, to_block)
+                .await?;
+
+            // Only mark the chunk as scanned once all of its commits were added.
+            self.last_scanned_block = Some(to_block);
+            self.first_scanned_block.get_or_insert(start_block);
+            from_block = to_block + 1;
+        }
+
+        let first_batch =
+            first_batch.or((total_committed > scan.total_executed).then_some(scan.total_executed));
+        if let Some(first_batch) = first_batch {
+            self.scan_back_to_batch(provider, &scan, first_batch)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Scans the blocks before `first_scanned_block` (newest first), until a given batch is in the index.
+    async fn scan_back_to_batch<P: Provider>(
+        &mut self,
+        provider: &P,
+        scan: &ScanContext,
+        batch_number: u64,
+    ) -> Result<(), Box<dyn Error>> {
+        while !self.stored.contains_key(&batch_number) {
+            let first_scanned = self.first_scanned_block.unwrap_or_default();
+            if first_scanned == 0 {
+                return Err(format!(
+                    "Batch {} not found in the commit events (scanned from the genesis block)",
+                    batch_number
+                )
+                .into());
+            }
+            let to_block = first_scanned - 1;
+            let from_block = to_block.saturating_sub(SCAN_CHUNK_SIZE - 1);
+            eprintln!(
+                "Batch {} is not in the index - scanning back blocks from {} to {}...",
+                batch_number, from_block, to_block
+            );
+
+            let mut older = BatchIndex::new(self.l1_chain_id, self.diamond_proxy);
+            older
+                .scan_chunk(provider, scan, from_block, to_block)
+                .await?;
+            self.merge_older(older);
+            self.first_scanned_block = Some(from_block);
+        }
+        Ok(())
+    }
+
+    /// Adds the batches from the blocks before the scanned ones - only the ones that we don't have yet,
+    /// and that were not reverted later.
+    fn merge_older(&mut self, older: BatchIndex) {
+        // Older commits of the batches above it were reverted.
+        let min_reverted_to = self.min_reverted_to();
+        for (batch_number, stored) in older.stored {
+            if self.stored.contains_key(&batch_number)
+                || min_reverted_to.is_some_and(|reverted_to| batch_number > reverted_to)
+            {
+                continue;
+            }
+            self.stored.insert(batch_number, stored);
+            if let Some(commit) = older.commits.get(&batch_number) {
+                self.commits.insert(batch_number, commit.clone());
+            }
+            if let Some(blob_hashes) = older.blob_hashes.get(&batch_number) {
+                self.blob_hashes.insert(batch_number, blob_hashes.clone());
+            }
+        }
+        for (block, total_committed) in older.reverts {
+            self.note_revert(block, total_committed);
+        }
+    }
+
+    /// Lowest `totalBatchesCommitted` of all the reverts in the scanned blocks.
+    pub fn min_reverted_to(&self) -> Option<u64> {
+        self.reverts.values().min().copied()
+    }
+
+    fn note_revert(&mut self, block: u64, total_committed: u64) {
+        self.reverts
+            .entry(block)
+            .and_modify(|reverted_to| *reverted_to = (*reverted_to).min(total_committed))
+            .or_insert(total_committed);
+    }
+
+    /// Applies the `BlockCommit` and `BlocksRevert` events from a given range of blocks.
+    async fn scan_chunk<P: Provider>(
+        &mut self,
+        provider: &P,
+        scan: &ScanContext,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<(), Box<dyn Error>> {
+        let filter = Filter::new()
+            .from_block(from_block)
+            .to_block(to_block)
+            .address(self.diamond_proxy)
+            .event_signature(vec![
+                IHyperchain::BlockCommit::SIGNATURE_HASH,
+                IHyperchain::BlocksRevert::SIGNATURE_HASH,
+            ]);
+
+        let mut logs = provider.get_logs(&filter).await?;
+        logs.sort_by_key(|log| (log.block_number, log.log_index));
+
+        // Single commit transaction emits one event per batch - so decode each one only once.
+        let mut commit_txs: HashMap<B256, CommitTx> = HashMap::new();
+        for log in logs {
+            if let Ok(event) = log.log_decode::<IHyperchain::BlocksRevert>() {
+                let total_committed: u64 = event.inner.data.totalBatchesCommitted.try_into()?;
+                eprintln!(
+                    "Batches reverted to {} at block {:?}",
+                    total_committed, log.block_number
+                );
+                self.revert_to(total_committed);
+                self.note_revert(
+                    log.block_number.ok_or("Log without block number")?,
+                    total_committed,
+                );
+                continue;
+            }
+            let event = log.log_decode::<IHyperchain::BlockCommit>()?.inner.data;
+            let tx_hash = log.transaction_hash.ok_or("Log without transaction hash")?;
+            let block_number = log.block_number.ok_or("Log without block number")?;
+
+            let commit_tx = match commit_txs.entry(tx_hash) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    eprintln!(
+                        "Found event in tx: {:?} at block {:?}",
+                        tx_hash, log.block_number
+                    );
+                    entry.insert(
+                        self.fetch_commit_tx(provider, tx_hash, block_number)
+                            .await?,
+                    )
+                }
+            };
+            self.add_commit(provider, commit_tx, &event, tx_hash, block_number, scan)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Fetches and decodes all the commits done in a given transaction (included in a given block).
+    /// Fails if none can be found - so that the chunk is not marked as scanned (and its batches are not lost).
+    async fn fetch_commit_tx<P: Provider>(
+        &self,
+        provider: &P,
+        tx_hash: B256,
+        block_number: u64,
+    ) -> Result<CommitTx, Box<dyn Error>> {
+        let tx_data = provider
+            .get_transaction_by_hash(tx_hash)
+            .await?
+            .ok_or("Transaction not found")?;
+
+        // Works for every envelope type (legacy, 2930, 1559 and 4844 blob transactions).
+        let commits =
+            find_commit_data(provider, tx_hash, tx_data.input(), self.diamond_proxy).await?;
+        if commits.is_empty() {
+            return Err(format!(
+                "Tx {} emitted BlockCommit, but its commit calldata can't be found (unknown wrapper, and the node doesn't support debug_traceTransaction)",
+                tx_hash
+            )
+            .into());
+        }
+
+        // The layout depends on the protocol version at the time of the commit (it may have been upgraded since).
+        let contract = IHyperchain::new(self.diamond_proxy, provider);
+        let protocol_version = ProtocolVersion::fetch_at(&contract, block_number).await?;
+
+        let mut commit_tx = CommitTx {
+            commits: HashMap::new(),
+            prev_stored: vec![],
+            blob_hashes: tx_data.blob_versioned_hashes().unwrap_or_default().to_vec(),
+        };
+        for commit_data in commits {
+            let commit_data =
+                decode_commit_data(&commit_data, protocol_version).map_err(|err| {
+                    format!("Failed to decode commit data in tx {}: {}", tx_hash, err)
+                })?;
+            commit_tx.prev_stored.push(commit_data.stored().clone());
+            match commit_data {
+                DecodedCommitData::BoojumOs { commits, .. } => {
+                    for commit in commits {
+                        commit_tx
+                            .commits
+                            .insert(commit.batchNumber, Commit::BoojumOs(commit));
+                    }
+                }
+                DecodedCommitData::Era { commits, .. } => {
+                    for commit in commits {
+                        commit_tx
+                            .commits
+                            .insert(commit.batchNumber, Commit::Era(commit));
+                    }
+                }
+            }
+        }
+        Ok(commit_tx)
+    }
+
+    /// Adds a batch from the commit transaction - after checking it against its `BlockCommit` event.
+    async fn add_commit<P: Provider>(
+        &mut self,
+        provider: &P,
+        commit_tx: &CommitTx,
+        event: &IHyperchain::BlockCommit,
+        tx_hash: B256,
+        block_number: u64,
+        scan: &ScanContext,
+    ) -> Result<(), Box<dyn Error>> {
+        let batch_number: u64 = event.batchNumber.try_into()?;
+        let commit = commit_tx.commits.get(&batch_number).ok_or_else(|| {
+            format!(
+                "Batch {} has BlockCommit event in tx {}, but is not in its calldata",
+                batch_number, tx_hash
+            )
+        })?;
+        let stored = match commit {
+            Commit::BoojumOs(commit) => {
+                let stored =
+                    commit_to_stored(commit.clone(), scan.upgrade.hash_for_batch(batch_number));
+                if stored.commitment == event.commitment {
+                    stored
+                } else {
+                    // Upgrade tx hash is cleared from the contract once the upgrade batch is executed -
+                    // so take the one that was pending when this batch was committed.
+                    let contract = IHyperchain::new(self.diamond_proxy, provider);
+                    let upgrade = UpgradeTx::fetch_at(&contract, block_number).await?;
+                    if upgrade.batch_number != batch_number {
+                        stored
+                    } else {
+                        eprintln!(
+                            "Batch {} included the protocol upgrade tx {}",
+                            batch_number, upgrade.tx_hash
+                        );
+                        commit_to_stored(commit.clone(), upgrade.tx_hash)
+                    }
+                }
+            }
+            Commit::Era(commit) => era_commit_to_stored(commit.clone(), event.commitment)?,
+        };
+        if stored.batchHash != event.batchHash || stored.commitment != event.commitment {
+            return Err(format!(
+                "Batch {} from tx {} doesn't match its BlockCommit event: decoded batch hash {} commitment {}, event batch hash {} commitment {}",
+                batch_number,
+                tx_hash,
+                stored.batchHash,
+                stored.commitment,
+                event.batchHash,
+                event.commitment
+            )
+            .into());
+        }
+
+        // Previous batches should already be there (from their own events) - unless they were
+        // committed before the scanned range.
+        for prev_stored in &commit_tx.prev_stored {
+            self.stored
+                .entry(prev_stored.batchNumber)
+                .or_insert_with(|| prev_stored.clone());
+        }
+
+        if commit_tx.blob_hashes.is_empty() {
+            self.blob_hashes.remove(&batch_number);
+        } else {
+            self.blob_hashes
+                .insert(batch_number, commit_tx.blob_hashes.clone());
+        }
+        self.stored.insert(batch_number, stored);
+        match commit {
+            Commit::BoojumOs(commit) => {
+                self.commits.insert(batch_number, commit.clone());
+            }
+            Commit::Era(_) => {
+                self.commits.remove(&batch_number);
+            }
+        }
+        Ok(())
+    }
+
+    /// Forgets all the batches above `total_committed` (they were reverted on L1).
+    pub fn revert_to(&mut self, total_committed: u64) {
+        self.commits.split_off(&(total_committed + 1));
+        self.stored.split_off(&(total_committed + 1));
+        self.blob_hashes.split_off(&(total_committed + 1));
+    }
+}
+
+/// State of the chain that is needed to decode the commits.
+struct ScanContext {
+    upgrade: UpgradeTx,
+    total_executed: u64,
+}
+
+enum Commit {
+    BoojumOs(CommitBoojumOSBatchInfo),
+    Era(CommitBatchInfo),
+}
+
+/// Commits done by a single L1 transaction.
+struct CommitTx {
+    commits: HashMap<u64, Commit>,
+    /// `StoredBatchInfo` of the batch preceding each commit.
+    prev_stored: Vec<StoredBatchInfo>,
+    blob_hashes: Vec<B256>,
+}
+
+/// Loads the index for a given diamond proxy, brings it up to date with L1 and saves it back.
+pub async fn sync_batch_index<P: Provider>(
+    provider: &P,
+    diamond_proxy: Address,
+    index_dir: &str,
+) -> Result<BatchIndex, Box<dyn Error>> {
+    sync_batch_index_from(provider, diamond_proxy, index_dir, None).await
+}
+
+/// Same as `sync_batch_index`, but also makes sure that the index has a given batch (and everything after it).
+pub async fn sync_batch_index_from<P: Provider>(
+    provider: &P,
+    diamond_proxy: Address,
+    index_dir: &str,
+    first_batch: Option<u64>,
+) -> Result<BatchIndex, Box<dyn Error>> {
+    let l1_chain_id = provider.get_chain_id().await?;
+    let mut index = BatchIndex::load(index_dir, l1_chain_id, diamond_proxy);
+    index.sync(provider, first_batch).await?;
+    index.save(index_dir)?;
+    Ok(index)
+}
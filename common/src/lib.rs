@@ -0,0 +1,173 @@
+// Things shared by all the tools that talk to the diamond proxy:
+// contract bindings, batch structures and the hashing that has to match the on-chain code.
+
+use alloy::sol;
+
+pub mod batch;
+pub mod bridgehub;
+pub mod commit_data;
+pub mod commit_tx;
+pub mod index;
+pub mod merkle;
+pub mod revert;
+pub mod verifier;
+
+pub use batch::{
+    UpgradeTx, check_batch_range, commit_to_stored, compute_batch_outputs_hash,
+    era_commit_to_stored, era_snark_public_input_for_batch, get_batch_public_input,
+    get_era_snark_public_input, shift_b256_right, snark_public_input_for_range,
+};
+pub use bridgehub::{get_bridgehub, get_chain_id, get_diamond_proxy, resolve_diamond_proxy};
+pub use commit_data::{DecodedCommitData, ProtocolVersion, decode_commit_data};
+pub use index::{BatchIndex, DEFAULT_INDEX_DIR, sync_batch_index, sync_batch_index_from};
+pub use merkle::MiniMerkleTree;
+pub use revert::{decode_revert_data, describe_call_error};
+pub use verifier::check_verification_key_hash;
+#[cfg(feature = "snark")]
+pub use verifier::{fflonk_vk_hash, snark_vk_hash};
+
+sol! {
+    #[sol(rpc)]
+    contract IHyperchain {
+        function getVerifier() external view returns (address);
+        function getAdmin() external view returns (address);
+        function getTotalBatchesCommitted() external view returns (uint256);
+        function getTotalBatchesVerified() external view returns (uint256);
+        function getTotalBatchesExecuted() external view returns (uint256);
+        function getSemverProtocolVersion() external view returns (uint32, uint32, uint32);
+
+        function getL2BootloaderBytecodeHash() external view returns (bytes32);
+        function getL2DefaultAccountBytecodeHash() external view returns (bytes32);
+        function getL2SystemContractsUpgradeTxHash() external view returns (bytes32);
+        function getL2SystemContractsUpgradeBatchNumber() external view returns (uint256);
+        function getChainId() external view returns (uint256);
+        function getSettlementLayer() external view returns (address);
+        function getChainTypeManager() external view returns (address);
+
+        function getPriorityQueueSize() external view returns (uint256);
+        function getTotalPriorityTxs() external view returns (uint256);
+        function getPriorityTreeRoot() external view returns (bytes32);
+        function getPriorityTreeStartIndex() external view returns (uint256);
+        function getFirstUnprocessedPriorityTx() external view returns (uint256);
+
+        function commitBatchesSharedBridge(
+            uint256, // _chainId
+            uint256 _processFrom,
+            uint256 _processTo,
+            bytes calldata commitData
+        );
+
+        // Layout of the `commitData` (after the version byte).
+        function commitDataPieces(StoredBatchInfo stored, CommitBoojumOSBatchInfo[] commits) external;
+        // Same, for the Era chains.
+        function commitDataPiecesEra(StoredBatchInfo stored, CommitBatchInfo[] commits) external;
+
+        // Layout of the `_proofData` (after the version byte).
+        function proofPayload(StoredBatchInfo old, StoredBatchInfo[] newInfo, uint256[] proof);
+
+        #[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+        struct PriorityOpsBatchInfo {
+            bytes32[] leftPath;
+            bytes32[] rightPath;
+            bytes32[] itemHashes;
+        }
+
+        // Layout of the `_executeData` (after the version byte).
+        function executePayload(StoredBatchInfo[] executeData, PriorityOpsBatchInfo[] priorityOps);
+
+        function proveBatchesSharedBridge(
+            uint256, // _chainId
+            uint256 _processBatchFrom,
+            uint256 _processBatchTo,
+            bytes calldata _proofData
+        );
+
+        function executeBatchesSharedBridge(
+            uint256, // _chainId
+            uint256 _processFrom,
+            uint256 _processTo,
+            bytes calldata _executeData
+        );
+
+        function revertBatchesSharedBridge(uint256 _chainId, uint256 _newLastBatch);
+
+        event BlockCommit(uint256 indexed batchNumber, bytes32 indexed batchHash, bytes32 indexed commitment);
+        event BlocksRevert(uint256 totalBatchesCommitted, uint256 totalBatchesVerified, uint256 totalBatchesExecuted);
+        // Emitted by the Mailbox facet for every priority operation (L1 -> L2 transaction).
+        event NewPriorityRequest(
+            uint256 txId,
+            bytes32 txHash,
+            uint64 expirationTimestamp,
+            L2CanonicalTransaction transaction,
+            bytes[] factoryDeps
+        );
+    }
+
+    #[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    struct StoredBatchInfo {
+        uint64 batchNumber;
+        bytes32 batchHash; // For Boojum OS batches we'll store here full state commitment
+        uint64 indexRepeatedStorageChanges; // For Boojum OS not used, 0
+        uint256 numberOfLayer1Txs;
+        bytes32 priorityOperationsHash;
+        bytes32 l2LogsTreeRoot;
+        uint256 timestamp; // For Boojum OS not used, 0
+        bytes32 commitment;// For Boojum OS batches we'll store batch output hash here
+    }
+
+    #[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    struct CommitBoojumOSBatchInfo {
+        uint64 batchNumber;
+        // chain state commitment, this preimage is not opened on l1,
+        // it's guaranteed that this commitment commits to any state that needed for execution
+        // (state root, block number, bloch hahes)
+        bytes32 newStateCommitment;
+        // info about processed l1 txs, l2 to l1 logs and DA
+        uint256 numberOfLayer1Txs;
+        bytes32 priorityOperationsHash;
+        bytes32 l2LogsTreeRoot;
+        address l2DaValidator; // TODO: already saved in the storage, can just add from there to PI
+        bytes32 daCommitment;
+        // sending used batch inputs to validate on the settlement layer
+        uint64 firstBlockTimestamp;
+        uint64 lastBlockTimestamp;
+        uint256 chainId; // TODO: already saved in the storage, can just add from there to PI
+        // extra calldata to pass to da validator
+        bytes operatorDAInput;
+    }
+
+    // Commit info of the Era (non Boojum OS) chains.
+    #[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    struct CommitBatchInfo {
+        uint64 batchNumber;
+        uint64 timestamp;
+        uint64 indexRepeatedStorageChanges;
+        bytes32 newStateRoot;
+        uint256 numberOfLayer1Txs;
+        bytes32 priorityOperationsHash;
+        bytes32 bootloaderHeapInitialContentsHash;
+        bytes32 eventsQueueStateHash;
+        bytes systemLogs;
+        bytes operatorDAInput;
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct L2CanonicalTransaction {
+        uint256 txType;
+        uint256 from;
+        uint256 to;
+        uint256 gasLimit;
+        uint256 gasPerPubdataByteLimit;
+        uint256 maxFeePerGas;
+        uint256 maxPriorityFeePerGas;
+        uint256 paymaster;
+        uint256 nonce;
+        uint256 value;
+        uint256[4] reserved;
+        bytes data;
+        bytes signature;
+        uint256[] factoryDeps;
+        bytes paymasterInput;
+        bytes reservedDynamic;
+    }
+}
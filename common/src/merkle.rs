@@ -0,0 +1,328 @@
+// Merkle tree of the priority operations - mirrors `DynamicIncrementalMerkle.Bytes32PushTree` from the contracts
+// (which backs the priority tree), together with the `Merkle` library checks that the executor does on its paths.
+// Based on the mini_merkle_tree crate from zksync-era.
+
+use std::{
+    collections::VecDeque,
+    error::Error,
+    ops::RangeInclusive,
+    sync::{LazyLock, OnceLock},
+};
+
+use alloy::primitives::{B256, keccak256};
+
+/// Depth of the deepest tree that we support.
+const MAX_TREE_DEPTH: usize = 32;
+
+/// Hashes of the empty subtrees of each depth (the empty leaf is `keccak256("")`).
+static EMPTY_SUBTREE_HASHES: LazyLock<Vec<B256>> = LazyLock::new(|| {
+    std::iter::successors(Some(keccak256([])), |hash| Some(compress(hash, hash)))
+        .take(MAX_TREE_DEPTH + 1)
+        .collect()
+});
+
+pub fn empty_subtree_hash(depth: usize) -> B256 {
+    EMPTY_SUBTREE_HASHES[depth]
+}
+
+pub fn compress(lhs: &B256, rhs: &B256) -> B256 {
+    keccak256([lhs.as_slice(), rhs.as_slice()].concat())
+}
+
+#[derive(Debug, Clone)]
+pub struct MiniMerkleTree {
+    /// Stores untrimmed (uncached) leaves of the tree.
+    hashes: VecDeque<B256>,
+    /// Size of the tree. Always a power of 2.
+    /// If it is greater than `self.start_index + self.hashes.len()`, the remaining leaves are empty.
+    binary_tree_size: usize,
+    /// Index of the leftmost untrimmed leaf.
+    start_index: usize,
+    /// Left subset of the Merkle path to the first untrimmed leaf (i.e., a leaf with index `self.start_index`).
+    /// Merkle path starts from the bottom of the tree and goes up.
+    /// Used to fill in data for trimmed tree leaves when computing Merkle paths and the root hash.
+    /// Because only the left subset of the path is used, the cache is not invalidated when new leaves are
+    /// pushed into the tree. If all leaves are trimmed, cache is the left subset of the Merkle path to
+    /// the next leaf to be inserted, which still has index `self.start_index`.
+    /// If the trimmed tree is also full, the last element is its root.
+    cache: Vec<Option<B256>>,
+    /// Hashes of all the levels - computed on the first use, and dropped whenever the tree changes.
+    levels: OnceLock<Levels>,
+}
+
+#[derive(Debug, Clone)]
+struct Levels {
+    /// Nodes of each level (from the leaves up), starting from the left sibling of the first untrimmed one
+    /// (at index `(start_index >> level) & !1`), and ending on a right sibling.
+    nodes: Vec<Vec<B256>>,
+    root: B256,
+}
+
+impl Default for MiniMerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MiniMerkleTree {
+    pub fn new() -> Self {
+        Self {
+            hashes: Default::default(),
+            binary_tree_size: 1,
+            start_index: 0,
+            cache: vec![],
+            levels: OnceLock::new(),
+        }
+    }
+
+    pub fn from_hashes(hashes: impl IntoIterator<Item = B256>) -> Self {
+        let mut tree = Self::new();
+        for hash in hashes {
+            tree.push_hash(hash);
+        }
+        tree
+    }
+
+    /// Number of leaves pushed into the tree (including the trimmed ones).
+    pub fn len(&self) -> usize {
+        self.start_index + self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Index of the first leaf that was not trimmed.
+    pub fn start_index(&self) -> usize {
+        self.start_index
+    }
+
+    /// Length of the Merkle paths.
+    pub fn depth(&self) -> usize {
+        tree_depth_by_size(self.binary_tree_size)
+    }
+
+    pub fn push_hash(&mut self, leaf_hash: B256) {
+        self.hashes.push_back(leaf_hash);
+        self.levels.take();
+        if self.len() > self.binary_tree_size {
+            self.binary_tree_size *= 2;
+            if self.cache.len() < self.depth() {
+                self.cache.push(None);
+            }
+        }
+    }
+
+    /// Root of the tree. Like in `Bytes32PushTree`, the root of an empty tree is zero.
+    pub fn root(&self) -> B256 {
+        if self.is_empty() {
+            return B256::ZERO;
+        }
+        self.levels().root
+    }
+
+    /// Root of the tree and the Merkle path to the leaf with a given index.
+    pub fn merkle_root_and_path(&self, index: usize) -> (B256, Vec<B256>) {
+        let (root, path, _) = self.merkle_root_and_paths_for_range(index..=index);
+        (root, path)
+    }
+
+    /// Root of the tree, and the Merkle paths to the first and the last leaf of the range
+    /// (what the executor needs to prove the priority operations of a batch).
+    pub fn merkle_root_and_paths_for_range(
+        &self,
+        range: RangeInclusive<usize>,
+    ) -> (B256, Vec<B256>, Vec<B256>) {
+        assert!(
+            range.start() <= range.end() && *range.end() < self.len(),
+            "Leaves {:?} are not in the tree (of {} leaves)",
+            range,
+            self.len()
+        );
+        let complete = |leaf| {
+            self.path(leaf)
+                .into_iter()
+                .map(|hash| hash.expect("Merkle path is incomplete"))
+                .collect()
+        };
+        (
+            self.levels().root,
+            complete(*range.start()),
+            complete(*range.end()),
+        )
+    }
+
+    /// Removes the first `count` untrimmed leaves - only the hashes needed for the paths to the
+    /// remaining ones are kept.
+    pub fn trim_start(&mut self, count: usize) {
+        assert!(self.hashes.len() >= count, "Not enough leaves to trim");
+        if count == 0 {
+            return;
+        }
+        let new_start_index = self.start_index + count;
+        let root = self.levels().root;
+        // Only the left siblings are needed.
+        let mut cache: Vec<Option<B256>> = self
+            .path(new_start_index)
+            .into_iter()
+            .enumerate()
+            .map(|(level, hash)| hash.filter(|_| (new_start_index >> level) % 2 == 1))
+            .collect();
+        if new_start_index == self.binary_tree_size {
+            // The tree is completely trimmed and will grow on the next push - so we need its root.
+            cache.push(Some(root));
+        }
+
+        self.hashes.drain(..count);
+        self.start_index = new_start_index;
+        self.cache = cache;
+        self.levels.take();
+    }
+
+    /// Merkle path to a given leaf. Siblings that are not known (trimmed, or past the end of the tree) are `None`.
+    fn path(&self, leaf: usize) -> Vec<Option<B256>> {
+        assert!(leaf >= self.start_index, "Leaf {} is trimmed", leaf);
+        self.levels()
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(level, nodes)| {
+                let level_start = (self.start_index >> level) & !1;
+                nodes.get(((leaf >> level) ^ 1) - level_start).copied()
+            })
+            .collect()
+    }
+
+    fn levels(&self) -> &Levels {
+        self.levels.get_or_init(|| self.compute_levels())
+    }
+
+    fn compute_levels(&self) -> Levels {
+        let depth = self.depth();
+        if self.hashes.is_empty() && self.start_index == self.binary_tree_size {
+            return Levels {
+                nodes: vec![],
+                root: self.cache[depth].expect("cache is invalid"),
+            };
+        }
+
+        let mut nodes = Vec::with_capacity(depth);
+        let mut level_hashes: Vec<B256> = self.hashes.iter().copied().collect();
+        for level in 0..depth {
+            let mut hashes = Vec::with_capacity(level_hashes.len() + 2);
+            // If the first untrimmed node is a right sibling, its left sibling comes from the cache.
+            if (self.start_index >> level) % 2 == 1 {
+                hashes.push(self.cache[level].expect("cache is invalid"));
+            }
+            hashes.extend(level_hashes);
+            // If the level ends on a left sibling (or there are no nodes at all), the right one is empty.
+            if hashes.is_empty() {
+                hashes.push(empty_subtree_hash(level));
+            }
+            if hashes.len() % 2 == 1 {
+                hashes.push(empty_subtree_hash(level));
+            }
+            level_hashes = hashes
+                .chunks(2)
+                .map(|pair| compress(&pair[0], &pair[1]))
+                .collect();
+            nodes.push(hashes);
+        }
+
+        Levels {
+            nodes,
+            root: level_hashes[0],
+        }
+    }
+}
+
+fn tree_depth_by_size(tree_size: usize) -> usize {
+    debug_assert!(tree_size.is_power_of_two());
+    tree_size.trailing_zeros() as usize
+}
+
+/// Root of the tree, given a leaf and the Merkle path to it (`Merkle.calculateRoot`).
+pub fn calculate_root(path: &[B256], mut index: usize, leaf: B256) -> B256 {
+    let mut hash = leaf;
+    for sibling in path {
+        hash = if index % 2 == 1 {
+            compress(sibling, &hash)
+        } else {
+            compress(&hash, sibling)
+        };
+        index /= 2;
+    }
+    hash
+}
+
+pub fn verify_path(root: B256, path: &[B256], index: usize, leaf: B256) -> bool {
+    index >> path.len() == 0 && calculate_root(path, index, leaf) == root
+}
+
+/// Root of the tree, given consecutive leaves (starting at `start_index`) and the paths to the first and the
+/// last one (`Merkle.calculateRootPaths` - this is how the executor checks the priority operations of a batch).
+pub fn calculate_root_paths(
+    left_path: &[B256],
+    right_path: &[B256],
+    mut start_index: usize,
+    item_hashes: &[B256],
+) -> Result<B256, Box<dyn Error>> {
+    let path_length = left_path.len();
+    if path_length != right_path.len() {
+        return Err(format!(
+            "Merkle path length mismatch: {} and {}",
+            path_length,
+            right_path.len()
+        )
+        .into());
+    }
+    if path_length > MAX_TREE_DEPTH {
+        return Err("Merkle path out of bounds".into());
+    }
+    let mut level_len = item_hashes.len();
+    // Edge case: a single-node tree.
+    if path_length == 0 && (start_index != 0 || level_len != 1) {
+        return Err("Merkle path is empty".into());
+    }
+    if level_len == 0 {
+        return Err("Nothing to prove".into());
+    }
+    if start_index + level_len > 1 << path_length {
+        return Err("Merkle index or height mismatch".into());
+    }
+
+    let mut hashes = item_hashes.to_vec();
+    for level in 0..path_length {
+        let parity = start_index % 2;
+        // We get an extra element on the next level if on the current level elements either
+        // start on an odd index (`parity == 1`) or end on an even index (`level_len % 2 == 1`).
+        let next_level_len = level_len / 2 + (parity | (level_len % 2));
+        for i in 0..next_level_len {
+            let lhs = if i == 0 && parity == 1 {
+                left_path[level]
+            } else {
+                hashes[2 * i - parity]
+            };
+            let rhs = if i == next_level_len - 1 && (start_index + level_len) % 2 == 1 {
+                right_path[level]
+            } else {
+                hashes[2 * i + 1 - parity]
+            };
+            hashes[i] = compress(&lhs, &rhs);
+        }
+        level_len = next_level_len;
+        start_index /= 2;
+    }
+    Ok(hashes[0])
+}
+
+pub fn verify_range(
+    root: B256,
+    left_path: &[B256],
+    right_path: &[B256],
+    start_index: usize,
+    item_hashes: &[B256],
+) -> bool {
+    calculate_root_paths(left_path, right_path, start_index, item_hashes)
+        .is_ok_and(|computed| computed == root)
+}
@@ -0,0 +1,71 @@
+// Decoding of the revert data returned by the diamond proxy (and the contracts it calls),
+// so that we can tell why a commit / prove / execute was rejected.
+
+use alloy::{
+    contract, hex, sol,
+    sol_types::{SolInterface, decode_revert_reason},
+};
+
+sol! {
+    // Custom errors of the Executor facet, ValidatorTimelock and DualVerifier
+    // (the shared ones - like `Unauthorized` or `InvalidProof` - are listed only once).
+    #[derive(Debug)]
+    interface DiamondProxyErrors {
+        // Executor facet
+        error BatchHashMismatch(bytes32 expected, bytes32 actual);
+        error BatchNumberMismatch(uint256 expectedBatchNumber, uint256 providedBatchNumber);
+        error CanOnlyProcessOneBatch();
+        error CantExecuteUnprovenBatches();
+        error CantRevertExecutedBatch();
+        error EmptyData();
+        error HashMismatch(bytes32 expected, bytes32 actual);
+        error IncorrectBatchChainId(uint256 expected, uint256 actual);
+        error InvalidBatchesDataLength(uint256 batchesDataLength, uint256 priorityOpsDataLength);
+        error InvalidProtocolVersion();
+        error MismatchL2DAValidator();
+        error MismatchNumberOfLayer1Txs(uint256 numberOfLayer1Txs, uint256 expectedLength);
+        error NonIncreasingTimestamp();
+        error NonSequentialBatch();
+        error NotHistoricalRoot(bytes32 root);
+        error PriorityOperationsRollingHashMismatch();
+        error PriorityOpsDataItemHashesLengthIsNotZero();
+        error PriorityOpsDataLeftPathLengthIsNotZero();
+        error PriorityOpsDataRightPathLengthIsNotZero();
+        error RevertedBatchNotAfterNewLastBatch();
+        error UnsupportedCommitBatchEncoding(uint8 version);
+        error UnsupportedExecuteBatchEncoding(uint8 version);
+        error UnsupportedProofBatchEncoding(uint8 version);
+        error ValueMismatch(uint256 expected, uint256 actual);
+        error VerifiedBatchesExceedsCommittedBatches();
+
+        // ValidatorTimelock
+        error AccessControlUnauthorizedAccount(address account, bytes32 neededRole);
+        error TimeNotReached(uint256 expectedTimestamp, uint256 actualTimestamp);
+        error Unauthorized(address caller);
+
+        // DualVerifier
+        error EmptyProofLength();
+        error InvalidMockProofLength();
+        error InvalidProof();
+        error UnknownVerifierType();
+    }
+}
+
+/// Human readable form of the revert data: the custom error with its arguments,
+/// `Error(string)` / `Panic(uint256)`, or just the raw data if we don't know it.
+pub fn decode_revert_data(data: &[u8]) -> String {
+    if let Ok(error) = DiamondProxyErrors::DiamondProxyErrorsErrors::abi_decode(data) {
+        return format!("{:?}", error);
+    }
+    // Verifiers revert with plain strings.
+    decode_revert_reason(data)
+        .unwrap_or_else(|| format!("unknown revert data 0x{}", hex::encode(data)))
+}
+
+/// Describes the error of a contract call - decoding the revert data, if there is any.
+pub fn describe_call_error(err: &contract::Error) -> String {
+    match err.as_revert_data() {
+        Some(data) => format!("reverted with {}", decode_revert_data(&data)),
+        None => err.to_string(),
+    }
+}
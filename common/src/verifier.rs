@@ -0,0 +1,116 @@
+// Checks of the verifier behind the diamond proxy (DualVerifier), before we send a proof to it.
+
+use std::error::Error;
+
+use alloy::{
+    primitives::{Address, B256, U256},
+    providers::Provider,
+    sol,
+};
+
+use crate::IHyperchain;
+
+#[cfg(feature = "snark")]
+use alloy::primitives::keccak256;
+#[cfg(feature = "snark")]
+use bellman::{
+    bn256::{Bn256, Fq},
+    pairing::{
+        CurveAffine,
+        ff::{PrimeField, PrimeFieldRepr},
+    },
+    plonk::better_better_cs::setup::VerificationKey,
+};
+#[cfg(feature = "snark")]
+use circuit_definitions::circuit_definitions::aux_layer::{
+    FflonkSnarkVerifierCircuitVK, ZkSyncSnarkWrapperCircuit,
+};
+
+sol! {
+    #[sol(rpc)]
+    interface IDualVerifier {
+        function verificationKeyHash(uint256 _verifierType) external view returns (bytes32);
+    }
+}
+
+/// Checks that the verifier of a given type (behind `getVerifier()`) uses the verification key with a given hash -
+/// so that we don't submit a proof made with a different key.
+pub async fn check_verification_key_hash<P: Provider + Clone>(
+    provider: &P,
+    diamond_proxy: Address,
+    verifier_type: u64,
+    local_hash: B256,
+) -> Result<(), Box<dyn Error>> {
+    let verifier = IHyperchain::new(diamond_proxy, provider.clone())
+        .getVerifier()
+        .call()
+        .await?;
+    let l1_hash = IDualVerifier::new(verifier, provider.clone())
+        .verificationKeyHash(U256::from(verifier_type))
+        .call()
+        .await?;
+    if l1_hash != local_hash {
+        return Err(format!(
+            "Verification key mismatch: verifier {} (type {}) has key hash {}, but the local key hash is {}",
+            verifier, verifier_type, l1_hash, local_hash
+        )
+        .into());
+    }
+    eprintln!(
+        "Verification key hash matches verifier {}: {}",
+        verifier, l1_hash
+    );
+    Ok(())
+}
+
+/// Hash of the verification key - the same as `verificationKeyHash()` of the L1 verifier generated from it.
+#[cfg(feature = "snark")]
+pub fn snark_vk_hash(
+    vk: &VerificationKey<Bn256, ZkSyncSnarkWrapperCircuit>,
+) -> Result<B256, Box<dyn Error>> {
+    let lookup_selector = vk
+        .lookup_selector_commitment
+        .ok_or("Verification key has no lookup selector commitment")?;
+    let lookup_table_type = vk
+        .lookup_table_type_commitment
+        .ok_or("Verification key has no lookup table type commitment")?;
+
+    let mut bytes = vec![];
+    for point in vk
+        .gate_setup_commitments
+        .iter()
+        .chain(&vk.gate_selectors_commitments)
+        .chain(&vk.permutation_commitments)
+        .chain([&lookup_selector])
+        .chain(&vk.lookup_tables_commitments)
+        .chain([&lookup_table_type])
+    {
+        let (x, y) = point.as_xy();
+        x.into_repr().write_be(&mut bytes)?;
+        y.into_repr().write_be(&mut bytes)?;
+    }
+    // Flag for using the recursive part.
+    Fq::default().into_repr().write_be(&mut bytes)?;
+    Ok(keccak256(bytes))
+}
+
+/// Hash of the FFLONK verification key - the same as `verificationKeyHash()` of the FFLONK verifier generated from it
+/// (which packs the number of inputs, C0, the non residues and the G2 elements).
+#[cfg(feature = "snark")]
+pub fn fflonk_vk_hash(vk: &FflonkSnarkVerifierCircuitVK) -> Result<B256, Box<dyn Error>> {
+    let mut bytes = U256::from(vk.num_inputs).to_be_bytes::<32>().to_vec();
+    let (x, y) = vk.c0.as_xy();
+    x.into_repr().write_be(&mut bytes)?;
+    y.into_repr().write_be(&mut bytes)?;
+    for non_residue in &vk.non_residues {
+        non_residue.into_repr().write_be(&mut bytes)?;
+    }
+    for point in &vk.g2_elements {
+        let (x, y) = point.as_xy();
+        // Like in the pairing precompile - the imaginary part goes first.
+        for coordinate in [x.c1, x.c0, y.c1, y.c0] {
+            coordinate.into_repr().write_be(&mut bytes)?;
+        }
+    }
+    Ok(keccak256(bytes))
+}
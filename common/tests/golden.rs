@@ -0,0 +1,383 @@
+// Golden vectors - computed independently from the Solidity encoding (abi.encodePacked / abi.encode).
+// If any of these change, the tools will no longer produce data that the contracts accept.
+
+use std::collections::HashMap;
+
+use alloy::{
+    hex,
+    primitives::{Address, B256, Bytes, U256, b256},
+    sol_types::{Revert, SolCall, SolError, SolEvent, SolValue},
+};
+use common::{
+    CommitBatchInfo, CommitBoojumOSBatchInfo, DecodedCommitData, IHyperchain, ProtocolVersion,
+    StoredBatchInfo, UpgradeTx, commit_to_stored, compute_batch_outputs_hash, decode_commit_data,
+    decode_revert_data, era_commit_to_stored, era_snark_public_input_for_batch,
+    get_batch_public_input, get_era_snark_public_input, revert::DiamondProxyErrors,
+    shift_b256_right, snark_public_input_for_range,
+};
+
+const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion {
+    major: 0,
+    minor: 28,
+    patch: 0,
+};
+
+/// Last protocol version with the Era layout of the commit data.
+const ERA_PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion {
+    major: 0,
+    minor: 27,
+    patch: 0,
+};
+
+fn prev_stored() -> StoredBatchInfo {
+    StoredBatchInfo {
+        batchNumber: 4,
+        batchHash: B256::repeat_byte(0x66),
+        indexRepeatedStorageChanges: 0,
+        numberOfLayer1Txs: U256::ZERO,
+        priorityOperationsHash: B256::repeat_byte(0x77),
+        l2LogsTreeRoot: B256::repeat_byte(0x88),
+        timestamp: U256::ZERO,
+        commitment: B256::repeat_byte(0x99),
+    }
+}
+
+fn commit_5() -> CommitBoojumOSBatchInfo {
+    CommitBoojumOSBatchInfo {
+        batchNumber: 5,
+        newStateCommitment: B256::repeat_byte(0x11),
+        numberOfLayer1Txs: U256::from(3),
+        priorityOperationsHash: B256::repeat_byte(0x22),
+        l2LogsTreeRoot: B256::repeat_byte(0x33),
+        l2DaValidator: Address::repeat_byte(0x44),
+        daCommitment: B256::repeat_byte(0x55),
+        firstBlockTimestamp: 1_700_000_000,
+        lastBlockTimestamp: 1_700_000_100,
+        chainId: U256::from(270),
+        operatorDAInput: Bytes::from_static(&[0xde, 0xad]),
+    }
+}
+
+fn commit_6() -> CommitBoojumOSBatchInfo {
+    CommitBoojumOSBatchInfo {
+        batchNumber: 6,
+        newStateCommitment: B256::repeat_byte(0xaa),
+        numberOfLayer1Txs: U256::ZERO,
+        firstBlockTimestamp: 1_700_000_100,
+        lastBlockTimestamp: 1_700_000_200,
+        ..commit_5()
+    }
+}
+
+#[test]
+fn selectors_match_contracts() {
+    assert_eq!(
+        IHyperchain::commitBatchesSharedBridgeCall::SELECTOR,
+        hex!("98f81962")
+    );
+    assert_eq!(
+        IHyperchain::proveBatchesSharedBridgeCall::SELECTOR,
+        hex!("e12a6137")
+    );
+    assert_eq!(
+        IHyperchain::executeBatchesSharedBridgeCall::SELECTOR,
+        hex!("cf02827d")
+    );
+    assert_eq!(
+        IHyperchain::BlockCommit::SIGNATURE_HASH,
+        b256!("8f2916b2f2d78cc5890ead36c06c0f6d5d112c7e103589947e8e2f0d6eddb763")
+    );
+    assert_eq!(
+        IHyperchain::getL2SystemContractsUpgradeBatchNumberCall::SELECTOR,
+        hex!("e5355c75")
+    );
+    assert_eq!(
+        IHyperchain::BlocksRevert::SIGNATURE_HASH,
+        b256!("8bd4b15ea7d1bc41ea9abc3fc487ccb89cd678a00786584714faa9d751c84ee5")
+    );
+    assert_eq!(
+        IHyperchain::NewPriorityRequest::SIGNATURE_HASH,
+        b256!("4531cd5795773d7101c17bdeb9f5ab7f47d7056017506f937083be5d6e77a382")
+    );
+}
+
+#[test]
+fn batch_outputs_hash() {
+    assert_eq!(
+        compute_batch_outputs_hash(&commit_5(), B256::ZERO),
+        b256!("cf8f0648a6f37a17aa8c2b75a4e96071783f296f59eb5de619b5e848091a8917")
+    );
+    // operatorDAInput is not part of the hash.
+    let mut other = commit_5();
+    other.operatorDAInput = Bytes::new();
+    assert_eq!(
+        compute_batch_outputs_hash(&other, B256::ZERO),
+        compute_batch_outputs_hash(&commit_5(), B256::ZERO)
+    );
+}
+
+#[test]
+fn batch_outputs_hash_with_upgrade() {
+    assert_eq!(
+        compute_batch_outputs_hash(&commit_5(), B256::repeat_byte(0xbb)),
+        b256!("1194f0efb7acbb93c975e3d93107a426f11eaf675af13a9717fbf9f45acd6a5e")
+    );
+
+    let upgrade = UpgradeTx {
+        tx_hash: B256::repeat_byte(0xbb),
+        batch_number: 5,
+    };
+    assert_eq!(upgrade.hash_for_batch(5), B256::repeat_byte(0xbb));
+    assert_eq!(upgrade.hash_for_batch(6), B256::ZERO);
+    // Upgrade that was not committed yet.
+    let pending = UpgradeTx {
+        tx_hash: B256::repeat_byte(0xbb),
+        batch_number: 0,
+    };
+    assert_eq!(pending.hash_for_batch(5), B256::ZERO);
+}
+
+#[test]
+fn commit_to_stored_fields() {
+    let stored = commit_to_stored(commit_5(), B256::ZERO);
+    assert_eq!(stored.batchNumber, 5);
+    assert_eq!(stored.batchHash, B256::repeat_byte(0x11));
+    assert_eq!(stored.indexRepeatedStorageChanges, 0);
+    assert_eq!(stored.numberOfLayer1Txs, U256::from(3));
+    assert_eq!(stored.priorityOperationsHash, B256::repeat_byte(0x22));
+    assert_eq!(stored.l2LogsTreeRoot, B256::repeat_byte(0x33));
+    assert_eq!(stored.timestamp, U256::ZERO);
+    assert_eq!(
+        stored.commitment,
+        b256!("cf8f0648a6f37a17aa8c2b75a4e96071783f296f59eb5de619b5e848091a8917")
+    );
+}
+
+#[test]
+fn stored_batch_info_abi_encoding() {
+    let expected = hex!(
+        "0000000000000000000000000000000000000000000000000000000000000004"
+        "6666666666666666666666666666666666666666666666666666666666666666"
+        "0000000000000000000000000000000000000000000000000000000000000000"
+        "0000000000000000000000000000000000000000000000000000000000000000"
+        "7777777777777777777777777777777777777777777777777777777777777777"
+        "8888888888888888888888888888888888888888888888888888888888888888"
+        "0000000000000000000000000000000000000000000000000000000000000000"
+        "9999999999999999999999999999999999999999999999999999999999999999"
+    );
+    assert_eq!(prev_stored().abi_encode(), expected);
+}
+
+#[test]
+fn commit_data_round_trip() {
+    let pieces = IHyperchain::commitDataPiecesCall {
+        stored: prev_stored(),
+        commits: vec![commit_5(), commit_6()],
+    };
+    let mut commit_data = vec![0u8];
+    pieces.abi_encode_raw(&mut commit_data);
+
+    assert_eq!(
+        decode_commit_data(&commit_data, PROTOCOL_VERSION).unwrap(),
+        DecodedCommitData::BoojumOs {
+            stored: prev_stored(),
+            commits: vec![commit_5(), commit_6()],
+        }
+    );
+
+    assert!(decode_commit_data(&[], PROTOCOL_VERSION).is_err());
+}
+
+fn era_commit_5() -> CommitBatchInfo {
+    // Two system logs: some other one, and the L2 to L1 logs tree root from the L1 messenger.
+    let mut system_logs = vec![0u8; 88];
+    system_logs[4..24].copy_from_slice(Address::repeat_byte(0x01).as_slice());
+    system_logs[56..88].copy_from_slice(B256::repeat_byte(0x02).as_slice());
+    let mut root_log = vec![0u8; 88];
+    root_log[22..24].copy_from_slice(&[0x80, 0x08]);
+    root_log[56..88].copy_from_slice(B256::repeat_byte(0x33).as_slice());
+    system_logs.extend(root_log);
+
+    CommitBatchInfo {
+        batchNumber: 5,
+        timestamp: 1_700_000_100,
+        indexRepeatedStorageChanges: 42,
+        newStateRoot: B256::repeat_byte(0x11),
+        numberOfLayer1Txs: U256::from(3),
+        priorityOperationsHash: B256::repeat_byte(0x22),
+        bootloaderHeapInitialContentsHash: B256::repeat_byte(0x44),
+        eventsQueueStateHash: B256::repeat_byte(0x55),
+        systemLogs: system_logs.into(),
+        operatorDAInput: Bytes::from_static(&[0xde, 0xad]),
+    }
+}
+
+#[test]
+fn era_commit_data_round_trip() {
+    let pieces = IHyperchain::commitDataPiecesEraCall {
+        stored: prev_stored(),
+        commits: vec![era_commit_5()],
+    };
+    let mut commit_data = vec![0u8];
+    pieces.abi_encode_raw(&mut commit_data);
+
+    assert_eq!(
+        decode_commit_data(&commit_data, ERA_PROTOCOL_VERSION).unwrap(),
+        DecodedCommitData::Era {
+            stored: prev_stored(),
+            commits: vec![era_commit_5()],
+        }
+    );
+    // The layout is picked by the protocol version - the Era data is not decoded as Boojum OS one.
+    let err = decode_commit_data(&commit_data, PROTOCOL_VERSION).unwrap_err();
+    assert!(err.to_string().contains("BoojumOs layout"), "{}", err);
+
+    let stored = era_commit_to_stored(era_commit_5(), B256::repeat_byte(0x99)).unwrap();
+    assert_eq!(stored.batchHash, B256::repeat_byte(0x11));
+    assert_eq!(stored.indexRepeatedStorageChanges, 42);
+    assert_eq!(stored.l2LogsTreeRoot, B256::repeat_byte(0x33));
+    assert_eq!(stored.timestamp, U256::from(1_700_000_100));
+    assert_eq!(stored.commitment, B256::repeat_byte(0x99));
+
+    // Without the logs tree root we would store a wrong batch - so it is an error.
+    let mut no_root = era_commit_5();
+    no_root.systemLogs = Default::default();
+    let err = era_commit_to_stored(no_root, B256::repeat_byte(0x99)).unwrap_err();
+    assert!(err.to_string().contains("Batch 5"), "{}", err);
+}
+
+#[test]
+fn commit_data_unknown_version() {
+    let pieces = IHyperchain::commitDataPiecesCall {
+        stored: prev_stored(),
+        commits: vec![commit_5()],
+    };
+    let mut commit_data = vec![1u8];
+    pieces.abi_encode_raw(&mut commit_data);
+    let err = decode_commit_data(&commit_data, PROTOCOL_VERSION).unwrap_err();
+    assert!(err.to_string().contains("encoding version 1"), "{}", err);
+
+    // Too old protocol version.
+    commit_data[0] = 0;
+    let old = ProtocolVersion {
+        major: 0,
+        minor: 24,
+        patch: 2,
+    };
+    let err = decode_commit_data(&commit_data, old).unwrap_err();
+    assert!(err.to_string().contains("0.24.2"), "{}", err);
+    // The whole version is compared (not just the minor one).
+    let next_major = ProtocolVersion {
+        major: 1,
+        minor: 0,
+        patch: 0,
+    };
+    assert!(decode_commit_data(&commit_data, next_major).is_ok());
+
+    // Garbage after a known version byte.
+    assert!(decode_commit_data(&[0, 1, 2, 3], PROTOCOL_VERSION).is_err());
+}
+
+#[test]
+fn public_inputs() {
+    let prev = prev_stored();
+    let batch_5 = commit_to_stored(commit_5(), B256::ZERO);
+    let public_input = get_batch_public_input(&prev, &batch_5);
+    assert_eq!(
+        public_input,
+        b256!("b86e1a01b3f18201e7efbacb5e2fd9205949bb93dc6488b7956cb62e1a073ed4")
+    );
+    assert_eq!(
+        shift_b256_right(&public_input),
+        b256!("00000000b86e1a01b3f18201e7efbacb5e2fd9205949bb93dc6488b7956cb62e")
+    );
+
+    let stored = HashMap::from([
+        (4, prev),
+        (5, batch_5),
+        (6, commit_to_stored(commit_6(), B256::ZERO)),
+    ]);
+    assert_eq!(
+        stored[&6].commitment,
+        b256!("38f24d5c7136ce233fc1ddbe315604dd83d688656ea65c4b655576dce2dd2e8b")
+    );
+    assert_eq!(
+        snark_public_input_for_range(&stored, 5, 5).unwrap(),
+        shift_b256_right(&public_input)
+    );
+    assert_eq!(
+        snark_public_input_for_range(&stored, 5, 6).unwrap(),
+        b256!("00000000953602e994c228b01ac068a6a32307d752e231472f56e3aa210f8980")
+    );
+    // Bad ranges are errors (not panics).
+    assert!(snark_public_input_for_range(&stored, 6, 5).is_err());
+    assert!(snark_public_input_for_range(&stored, 0, 5).is_err());
+    assert!(snark_public_input_for_range(&stored, 4, 5).is_err());
+    assert!(snark_public_input_for_range(&stored, 5, 7).is_err());
+}
+
+// Era (FFLONK / PLONK) proofs commit to keccak(prev.commitment, commitment) >> 32 - not to the Boojum OS input.
+// Here the commitments are 0x99..99 and 0xaa..aa: keccak256 of their concatenation is
+// 0xd85b2abebd029d4618f333ed1bd8b971810b03ef0aa0339afefccf710d738354, and the last 4 bytes are shifted out.
+#[test]
+fn era_public_input() {
+    let prev = era_commit_to_stored(era_commit_5(), B256::repeat_byte(0x99)).unwrap();
+    let mut commit_6 = era_commit_5();
+    commit_6.batchNumber = 6;
+    let batch_6 = era_commit_to_stored(commit_6, B256::repeat_byte(0xaa)).unwrap();
+
+    let expected = b256!("00000000d85b2abebd029d4618f333ed1bd8b971810b03ef0aa0339afefccf71");
+    assert_eq!(get_era_snark_public_input(&prev, &batch_6), expected);
+    assert_ne!(
+        shift_b256_right(&get_batch_public_input(&prev, &batch_6)),
+        expected
+    );
+
+    let stored = HashMap::from([(5, prev), (6, batch_6)]);
+    assert_eq!(
+        era_snark_public_input_for_batch(&stored, 6).unwrap(),
+        expected
+    );
+    assert!(era_snark_public_input_for_batch(&stored, 5).is_err());
+}
+
+#[test]
+fn revert_errors() {
+    assert_eq!(DiamondProxyErrors::InvalidProof::SELECTOR, hex!("09bde339"));
+    assert_eq!(
+        DiamondProxyErrors::BatchHashMismatch::SELECTOR,
+        hex!("55ad3fd3")
+    );
+    assert_eq!(
+        DiamondProxyErrors::UnknownVerifierType::SELECTOR,
+        hex!("c352bb73")
+    );
+    assert_eq!(
+        DiamondProxyErrors::InvalidMockProofLength::SELECTOR,
+        hex!("d08a97e6")
+    );
+
+    assert_eq!(
+        decode_revert_data(&hex!("09bde339")),
+        "InvalidProof(InvalidProof)"
+    );
+    let mismatch = DiamondProxyErrors::BatchHashMismatch {
+        expected: B256::repeat_byte(0x11),
+        actual: B256::repeat_byte(0x22),
+    }
+    .abi_encode();
+    let decoded = decode_revert_data(&mismatch);
+    assert!(decoded.starts_with("BatchHashMismatch"), "{}", decoded);
+    assert!(
+        decoded.contains(&B256::repeat_byte(0x22).to_string()),
+        "{}",
+        decoded
+    );
+
+    let reason = Revert::from("loadProof: Proof is invalid").abi_encode();
+    assert!(decode_revert_data(&reason).contains("loadProof: Proof is invalid"));
+    assert_eq!(
+        decode_revert_data(&hex!("deadbeef")),
+        "unknown revert data 0xdeadbeef"
+    );
+}
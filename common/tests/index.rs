@@ -0,0 +1,295 @@
+// Syncing the batch index against a mocked L1 (the responses are returned in the order of the requests).
+
+use std::collections::BTreeMap;
+
+use alloy::{
+    primitives::{Address, B256, Bytes, U256},
+    providers::{ProviderBuilder, mock::Asserter},
+    rpc::types::Log,
+    sol_types::{SolCall, SolEvent, SolValue},
+};
+use common::{BatchIndex, CommitBoojumOSBatchInfo, IHyperchain, StoredBatchInfo, commit_to_stored};
+use serde_json::json;
+
+const DIAMOND_PROXY: Address = Address::repeat_byte(0xdd);
+
+fn commit(batch_number: u64, state: u8) -> CommitBoojumOSBatchInfo {
+    CommitBoojumOSBatchInfo {
+        batchNumber: batch_number,
+        newStateCommitment: B256::repeat_byte(state),
+        numberOfLayer1Txs: U256::ZERO,
+        priorityOperationsHash: B256::ZERO,
+        l2LogsTreeRoot: B256::ZERO,
+        l2DaValidator: Address::ZERO,
+        daCommitment: B256::ZERO,
+        firstBlockTimestamp: 0,
+        lastBlockTimestamp: 0,
+        chainId: U256::from(270),
+        operatorDAInput: Bytes::new(),
+    }
+}
+
+fn stored(commit: &CommitBoojumOSBatchInfo) -> StoredBatchInfo {
+    commit_to_stored(commit.clone(), B256::ZERO)
+}
+
+/// Batch that was committed before the scanned blocks (only seen as the `stored` of the commit data).
+fn genesis_stored(batch_number: u64) -> StoredBatchInfo {
+    stored(&commit(batch_number, 0x01))
+}
+
+/// Commit transaction with its `BlockCommit` logs.
+struct CommitTx {
+    hash: B256,
+    prev: StoredBatchInfo,
+    commits: Vec<CommitBoojumOSBatchInfo>,
+}
+
+impl CommitTx {
+    fn new(hash: u8, prev: StoredBatchInfo, commits: Vec<CommitBoojumOSBatchInfo>) -> Self {
+        Self {
+            hash: B256::repeat_byte(hash),
+            prev,
+            commits,
+        }
+    }
+
+    fn logs(&self, block_number: u64) -> Vec<Log> {
+        self.commits
+            .iter()
+            .map(|commit| {
+                let stored = stored(commit);
+                let event = IHyperchain::BlockCommit {
+                    batchNumber: U256::from(commit.batchNumber),
+                    batchHash: stored.batchHash,
+                    commitment: stored.commitment,
+                };
+                log(event.encode_log_data(), block_number, Some(self.hash))
+            })
+            .collect()
+    }
+
+    fn json(&self) -> serde_json::Value {
+        let pieces = IHyperchain::commitDataPiecesCall {
+            stored: self.prev.clone(),
+            commits: self.commits.clone(),
+        };
+        let mut commit_data = vec![0u8];
+        pieces.abi_encode_raw(&mut commit_data);
+        let input = IHyperchain::commitBatchesSharedBridgeCall {
+            _0: U256::from(270),
+            _processFrom: U256::from(self.commits[0].batchNumber),
+            _processTo: U256::from(self.commits.last().unwrap().batchNumber),
+            commitData: commit_data.into(),
+        }
+        .abi_encode();
+        json!({
+            "type": "0x2",
+            "chainId": "0x1",
+            "nonce": "0x0",
+            "gas": "0x100000",
+            "maxFeePerGas": "0x1",
+            "maxPriorityFeePerGas": "0x1",
+            "to": DIAMOND_PROXY,
+            "value": "0x0",
+            "accessList": [],
+            "input": Bytes::from(input),
+            "r": "0x1",
+            "s": "0x1",
+            "yParity": "0x0",
+            "v": "0x0",
+            "hash": self.hash,
+            "from": Address::repeat_byte(0x01),
+            "gasPrice": "0x1",
+        })
+    }
+}
+
+fn revert_log(total_committed: u64, block_number: u64) -> Log {
+    let event = IHyperchain::BlocksRevert {
+        totalBatchesCommitted: U256::from(total_committed),
+        totalBatchesVerified: U256::ZERO,
+        totalBatchesExecuted: U256::ZERO,
+    };
+    log(
+        event.encode_log_data(),
+        block_number,
+        Some(B256::repeat_byte(0xee)),
+    )
+}
+
+fn log(data: alloy::primitives::LogData, block_number: u64, tx_hash: Option<B256>) -> Log {
+    Log {
+        inner: alloy::primitives::Log {
+            address: DIAMOND_PROXY,
+            data,
+        },
+        block_number: Some(block_number),
+        // One event per block is enough here.
+        log_index: Some(0),
+        transaction_hash: tx_hash,
+        ..Default::default()
+    }
+}
+
+/// Responses to the calls done at the beginning of `sync`.
+fn push_sync_start(
+    asserter: &Asserter,
+    latest_block: u64,
+    total_committed: u64,
+    total_executed: u64,
+) {
+    asserter.push_success(&U256::from(latest_block));
+    // getTotalBatchesCommitted, upgrade tx hash & batch number, getTotalBatchesExecuted
+    asserter.push_success(&Bytes::from(U256::from(total_committed).abi_encode()));
+    asserter.push_success(&Bytes::from(B256::ZERO.abi_encode()));
+    asserter.push_success(&Bytes::from(U256::ZERO.abi_encode()));
+    asserter.push_success(&Bytes::from(U256::from(total_executed).abi_encode()));
+}
+
+/// Responses to fetching a commit transaction: the transaction, and the protocol version at its block.
+fn push_commit_tx(asserter: &Asserter, tx: &CommitTx) {
+    asserter.push_success(&tx.json());
+    asserter.push_success(&Bytes::from(
+        (U256::ZERO, U256::from(28), U256::ZERO).abi_encode_params(),
+    ));
+}
+
+#[tokio::test]
+async fn revert_then_recommit() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+
+    let first = CommitTx::new(
+        0xa1,
+        genesis_stored(4),
+        vec![commit(5, 0x05), commit(6, 0x06)],
+    );
+    let recommit = CommitTx::new(0xa2, stored(&commit(5, 0x05)), vec![commit(6, 0x16)]);
+
+    push_sync_start(&asserter, 100, 6, 4);
+    // Commit 5 & 6, revert 6, and commit a different 6 - all in a single chunk.
+    let mut logs = first.logs(10);
+    logs.push(revert_log(5, 11));
+    logs.extend(recommit.logs(12));
+    asserter.push_success(&logs);
+    push_commit_tx(&asserter, &first);
+    push_commit_tx(&asserter, &recommit);
+
+    let mut index = BatchIndex::new(1, DIAMOND_PROXY);
+    index.sync(&provider, None).await.unwrap();
+
+    assert_eq!(index.stored.keys().copied().collect::<Vec<_>>(), [4, 5, 6]);
+    assert_eq!(index.stored[&6], stored(&commit(6, 0x16)));
+    assert_eq!(index.commits[&6], commit(6, 0x16));
+    assert_eq!(index.last_scanned_block, Some(100));
+    assert_eq!(index.reverts, BTreeMap::from([(11, 5)]));
+
+    // Revert below the re-committed batch (in the next sync) - both are gone, with their commit info.
+    push_sync_start(&asserter, 200, 4, 4);
+    asserter.push_success(&vec![revert_log(4, 150)]);
+    index.sync(&provider, None).await.unwrap();
+    assert_eq!(index.stored.keys().copied().collect::<Vec<_>>(), [4]);
+    assert!(index.commits.is_empty());
+    assert_eq!(index.reverts, BTreeMap::from([(11, 5), (150, 4)]));
+    assert_eq!(index.min_reverted_to(), Some(4));
+}
+
+#[tokio::test]
+async fn commitment_mismatch() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+
+    // Executed upgrade batch - its commitment includes the upgrade tx hash, which is no longer in the contract.
+    let upgrade_tx_hash = B256::repeat_byte(0x42);
+    let tx = CommitTx::new(0xc1, genesis_stored(4), vec![commit(5, 0x05)]);
+    let mut logs = tx.logs(10);
+    logs[0].inner.data.topics_mut()[3] =
+        commit_to_stored(commit(5, 0x05), upgrade_tx_hash).commitment;
+
+    push_sync_start(&asserter, 100, 5, 5);
+    asserter.push_success(&logs);
+    push_commit_tx(&asserter, &tx);
+    // Upgrade tx hash & batch number at the block of the commit.
+    asserter.push_success(&Bytes::from(upgrade_tx_hash.abi_encode()));
+    asserter.push_success(&Bytes::from(U256::from(5).abi_encode()));
+    let mut index = BatchIndex::new(1, DIAMOND_PROXY);
+    index.sync(&provider, None).await.unwrap();
+    assert_eq!(
+        index.stored[&5],
+        commit_to_stored(commit(5, 0x05), upgrade_tx_hash)
+    );
+
+    // Any other batch must match the event - executed or not.
+    for total_executed in [5, 4] {
+        push_sync_start(&asserter, 100, 5, total_executed);
+        asserter.push_success(&logs);
+        push_commit_tx(&asserter, &tx);
+        asserter.push_success(&Bytes::from(upgrade_tx_hash.abi_encode()));
+        asserter.push_success(&Bytes::from(U256::from(7).abi_encode()));
+        let mut index = BatchIndex::new(1, DIAMOND_PROXY);
+        let err = index.sync(&provider, None).await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("doesn't match its BlockCommit event"),
+            "{}",
+            err
+        );
+    }
+}
+
+#[tokio::test]
+async fn scans_back_to_the_first_batch() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+
+    // Old commit (before the initial scan window) of 4, 5 and 6 - then 5 and 6 were reverted,
+    // and a different 5 was committed.
+    let old = CommitTx::new(
+        0xb1,
+        genesis_stored(3),
+        vec![commit(4, 0x04), commit(5, 0x05), commit(6, 0x06)],
+    );
+    let recommit = CommitTx::new(0xb2, stored(&commit(4, 0x04)), vec![commit(5, 0x15)]);
+
+    // Initial scan covers blocks 500_000 - 1_500_000 (in chunks of 10_000).
+    push_sync_start(&asserter, 1_500_000, 5, 3);
+    for chunk_start in (500_000..=1_500_000).step_by(10_000) {
+        if chunk_start == 600_000 {
+            let mut logs = vec![revert_log(4, 600_000)];
+            logs.extend(recommit.logs(600_001));
+            asserter.push_success(&logs);
+            push_commit_tx(&asserter, &recommit);
+        } else {
+            asserter.push_success(&Vec::<Log>::new());
+        }
+    }
+    // Batch 3 is not there - so the previous chunk is scanned.
+    asserter.push_success(&old.logs(495_000));
+    push_commit_tx(&asserter, &old);
+
+    let mut index = BatchIndex::new(1, DIAMOND_PROXY);
+    index.sync(&provider, Some(3)).await.unwrap();
+
+    assert_eq!(index.first_scanned_block, Some(490_000));
+    assert_eq!(index.reverts, BTreeMap::from([(600_000, 4)]));
+    assert_eq!(index.last_scanned_block, Some(1_500_000));
+    // The old 6 was reverted later, and 5 was re-committed - only the missing 3 is taken from the old commit.
+    assert_eq!(index.stored.keys().copied().collect::<Vec<_>>(), [3, 4, 5]);
+    assert_eq!(index.stored[&3], genesis_stored(3));
+    assert_eq!(index.stored[&5], stored(&commit(5, 0x15)));
+    assert!(!index.commits.contains_key(&6));
+}
+
+#[tokio::test]
+async fn scanning_back_stops_at_genesis() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+
+    push_sync_start(&asserter, 5, 0, 0);
+    asserter.push_success(&Vec::<Log>::new());
+
+    let mut index = BatchIndex::new(1, DIAMOND_PROXY);
+    let err = index.sync(&provider, Some(3)).await.unwrap_err();
+    assert!(err.to_string().contains("Batch 3 not found"), "{}", err);
+}
@@ -0,0 +1,204 @@
+// MiniMerkleTree checked against the priority tree from the contracts:
+// `DynamicIncrementalMerkle.Bytes32PushTree` (ported below) and `Merkle.calculateRootPaths`.
+
+use alloy::primitives::{B256, U256, b256, keccak256};
+use common::{
+    MiniMerkleTree,
+    merkle::{calculate_root, calculate_root_paths, compress, verify_path, verify_range},
+};
+
+/// Port of `DynamicIncrementalMerkle.Bytes32PushTree` (`setup` with `keccak256("")`, `push` and `root`).
+struct Bytes32PushTree {
+    next_leaf_index: usize,
+    sides: Vec<B256>,
+    zeros: Vec<B256>,
+}
+
+impl Bytes32PushTree {
+    fn setup() -> Self {
+        Self {
+            next_leaf_index: 0,
+            sides: vec![B256::ZERO],
+            zeros: vec![keccak256([])],
+        }
+    }
+
+    /// Returns the new root.
+    fn push(&mut self, leaf: B256) -> B256 {
+        let mut levels = self.zeros.len() - 1;
+        let index = self.next_leaf_index;
+        self.next_leaf_index += 1;
+        // Check if the tree is full.
+        if index == 1 << levels {
+            let zero = self.zeros[levels];
+            self.zeros.push(compress(&zero, &zero));
+            self.sides.push(B256::ZERO);
+            levels += 1;
+        }
+        // Rebuild the branch from the leaf to the root.
+        let mut current_level_hash = leaf;
+        let mut updated_sides = false;
+        for i in 0..levels {
+            let is_left = (index >> i) & 1 == 0;
+            if is_left && !updated_sides {
+                self.sides[i] = current_level_hash;
+                updated_sides = true;
+            }
+            current_level_hash = if is_left {
+                compress(&current_level_hash, &self.zeros[i])
+            } else {
+                compress(&self.sides[i], &current_level_hash)
+            };
+        }
+        self.sides[levels] = current_level_hash;
+        current_level_hash
+    }
+
+    fn root(&self) -> B256 {
+        *self.sides.last().unwrap()
+    }
+}
+
+fn leaf(i: usize) -> B256 {
+    keccak256(U256::from(i).to_be_bytes::<32>())
+}
+
+fn leaves(count: usize) -> Vec<B256> {
+    (0..count).map(leaf).collect()
+}
+
+#[test]
+fn empty_tree_root() {
+    assert_eq!(MiniMerkleTree::new().root(), B256::ZERO);
+    assert_eq!(Bytes32PushTree::setup().root(), B256::ZERO);
+}
+
+#[test]
+fn small_tree_roots() {
+    let empty_leaf = b256!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470");
+    let tree = MiniMerkleTree::from_hashes(leaves(1));
+    assert_eq!(tree.root(), leaf(0));
+    assert_eq!(tree.depth(), 0);
+
+    let tree = MiniMerkleTree::from_hashes(leaves(3));
+    assert_eq!(
+        tree.root(),
+        compress(
+            &compress(&leaf(0), &leaf(1)),
+            &compress(&leaf(2), &empty_leaf)
+        )
+    );
+    assert_eq!(tree.depth(), 2);
+}
+
+#[test]
+fn roots_match_push_tree() {
+    let mut push_tree = Bytes32PushTree::setup();
+    let mut tree = MiniMerkleTree::new();
+    for i in 0..130 {
+        let root = push_tree.push(leaf(i));
+        tree.push_hash(leaf(i));
+        assert_eq!(tree.root(), root, "root after {} leaves", i + 1);
+        assert_eq!(push_tree.root(), root);
+    }
+}
+
+#[test]
+fn paths_verify() {
+    for count in 1..=33 {
+        let tree = MiniMerkleTree::from_hashes(leaves(count));
+        for index in 0..count {
+            let (root, path) = tree.merkle_root_and_path(index);
+            assert_eq!(root, tree.root());
+            assert_eq!(path.len(), tree.depth());
+            assert_eq!(calculate_root(&path, index, leaf(index)), root);
+            assert!(verify_path(root, &path, index, leaf(index)));
+            assert!(!verify_path(root, &path, index, leaf(count)));
+            assert!(!verify_path(root, &path, index ^ 1, leaf(index)) || count == 1);
+        }
+    }
+}
+
+#[test]
+fn range_proofs_verify() {
+    for count in 1..=20 {
+        let hashes = leaves(count);
+        let tree = MiniMerkleTree::from_hashes(hashes.clone());
+        for first in 0..count {
+            for last in first..count {
+                let (root, left_path, right_path) =
+                    tree.merkle_root_and_paths_for_range(first..=last);
+                let items = &hashes[first..=last];
+                assert_eq!(
+                    calculate_root_paths(&left_path, &right_path, first, items).unwrap(),
+                    root,
+                    "range {}..={} of {}",
+                    first,
+                    last,
+                    count
+                );
+                assert!(verify_range(root, &left_path, &right_path, first, items));
+                // Shifted by one - must not verify.
+                if first + items.len() < count {
+                    assert!(!verify_range(
+                        root,
+                        &left_path,
+                        &right_path,
+                        first + 1,
+                        items
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn range_proof_errors() {
+    let tree = MiniMerkleTree::from_hashes(leaves(4));
+    let (_, left_path, right_path) = tree.merkle_root_and_paths_for_range(1..=2);
+    assert!(calculate_root_paths(&left_path, &right_path[..1], 1, &leaves(4)[1..=2]).is_err());
+    assert!(calculate_root_paths(&left_path, &right_path, 1, &[]).is_err());
+    assert!(calculate_root_paths(&left_path, &right_path, 3, &leaves(4)[1..=2]).is_err());
+    assert!(calculate_root_paths(&[], &[], 0, &leaves(2)).is_err());
+    assert_eq!(
+        calculate_root_paths(&[], &[], 0, &[leaf(0)]).unwrap(),
+        leaf(0)
+    );
+}
+
+#[test]
+fn trimmed_tree_matches_full_tree() {
+    for count in 1..=17 {
+        for trimmed in 0..=count {
+            let mut tree = MiniMerkleTree::from_hashes(leaves(count));
+            // Trim in two steps, to also check trimming an already trimmed tree.
+            tree.trim_start(trimmed / 2);
+            tree.trim_start(trimmed - trimmed / 2);
+            assert_eq!(tree.start_index(), trimmed);
+
+            let mut full_tree = MiniMerkleTree::from_hashes(leaves(count));
+            // Keep pushing after trimming - the root and the paths must stay the same as in the full tree.
+            for extra in 0..10 {
+                assert_eq!(
+                    tree.root(),
+                    full_tree.root(),
+                    "{} leaves, {} trimmed",
+                    count + extra,
+                    trimmed
+                );
+                let last = count + extra - 1;
+                for range in [trimmed..=last, last..=last, trimmed..=trimmed] {
+                    if trimmed <= last {
+                        assert_eq!(
+                            tree.merkle_root_and_paths_for_range(range.clone()),
+                            full_tree.merkle_root_and_paths_for_range(range)
+                        );
+                    }
+                }
+                tree.push_hash(leaf(count + extra));
+                full_tree.push_hash(leaf(count + extra));
+            }
+        }
+    }
+}
@@ -1,49 +1,13 @@
 use alloy::{
-    primitives::{Address, B256, FixedBytes, keccak256},
+    primitives::{Address, FixedBytes, keccak256},
     signers::local::PrivateKeySigner,
 };
 use clap::Parser;
 
 use alloy::{hex::FromHex, providers::ProviderBuilder, sol};
+use common::{IHyperchain, resolve_diamond_proxy};
 
 sol! {
-    #[sol(rpc)]
-    contract IHyperchain {
-        function getVerifier() external view returns (address);
-        function getAdmin() external view returns (address);
-        function getTotalBatchesCommitted() external view returns (uint256);
-        function getTotalBatchesVerified() external view returns (uint256);
-        function getTotalBatchesExecuted() external view returns (uint256);
-        function getSemverProtocolVersion() external view returns (uint32, uint32, uint32);
-
-        function getL2BootloaderBytecodeHash() external view returns (bytes32);
-        function getL2DefaultAccountBytecodeHash() external view returns (bytes32);
-        function getL2SystemContractsUpgradeTxHash() external view returns (bytes32);
-        function getChainId() external view returns (uint256);
-        function getSettlementLayer() external view returns (address);
-
-        function getPriorityQueueSize() external view returns (uint256);
-        function getTotalPriorityTxs() external view returns (uint256);
-        function getPriorityTreeRoot() external view returns (bytes32);
-
-        function commitBatchesSharedBridge(uint256,uint256,uint256,bytes);
-
-        function tmpStuff(StoredBatchInfo stored, CommitBoojumOSBatchInfo[] commits) external;
-
-        function proofPayload(StoredBatchInfo old, StoredBatchInfo[] newInfo, uint256[] proof);
-
-        function proveBatchesSharedBridge(
-            uint256, // _chainId
-            uint256 _processBatchFrom,
-            uint256 _processBatchTo,
-            bytes calldata _proofData
-        );
-        event BlockCommit(uint256 indexed batchNumber, bytes32 indexed batchHash, bytes32 indexed commitment);
-
-        function getChainTypeManager() external view returns (address);
-
-    }
-
     enum Action {
         Add,
         Replace,
@@ -102,68 +66,23 @@ sol! {
         address public owner;
         function multicall(Call[] calldata _calls, bool _requireSuccess) external payable;
     }
-
-
-
-
-    #[derive(Debug)]
-
-    struct StoredBatchInfo {
-        uint64 batchNumber;
-        bytes32 batchHash; // For Boojum OS batches we'll store here full state commitment
-        uint64 indexRepeatedStorageChanges; // For Boojum OS not used, 0
-        uint256 numberOfLayer1Txs;
-        bytes32 priorityOperationsHash;
-        bytes32 l2LogsTreeRoot;
-        uint256 timestamp; // For Boojum OS not used, 0
-        bytes32 commitment;// For Boojum OS batches we'll store batch output hash here
-    }
-    #[derive(Debug)]
-
-    struct CommitBoojumOSBatchInfo {
-        uint64 batchNumber;
-        // chain state commitment, this preimage is not opened on l1,
-        // it's guaranteed that this commitment commits to any state that needed for execution
-        // (state root, block number, bloch hahes)
-        bytes32 newStateCommitment;
-        // info about processed l1 txs, l2 to l1 logs and DA
-        uint256 numberOfLayer1Txs;
-        bytes32 priorityOperationsHash;
-        bytes32 l2LogsTreeRoot;
-        address l2DaValidator; // TODO: already saved in the storage, can just add from there to PI
-        bytes32 daCommitment;
-        // sending used batch inputs to validate on the settlement layer
-        uint64 firstBlockTimestamp;
-        uint64 lastBlockTimestamp;
-        uint256 chainId; // TODO: already saved in the storage, can just add from there to PI
-        // extra calldata to pass to da validator
-        bytes operatorDAInput;
-    }
-
-    #[derive(Debug)]
-
-    struct Merged {
-        StoredBatchInfo foo;
-        CommitBoojumOSBatchInfo[] bar;
-    }
-}
-
-pub fn get_batch_public_input(prev_batch: &StoredBatchInfo, batch: &StoredBatchInfo) -> B256 {
-    let mut bytes = Vec::with_capacity(32 * 3);
-    bytes.extend_from_slice(prev_batch.batchHash.as_slice());
-    bytes.extend_from_slice(batch.batchHash.as_slice());
-    bytes.extend_from_slice(batch.commitment.as_slice());
-    keccak256(&bytes).into()
 }
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
+    /// Address of the diamond proxy - if not given, it is taken from the bridgehub (`--bridgehub`, or the one
+    /// of `--l2-rpc`) for `--chain-id`.
     #[arg(short, long)]
-    address: String,
+    address: Option<String>,
     #[arg(short, long)]
     server_url: Option<String>,
 
+    #[arg(long)]
+    bridgehub: Option<String>,
+    #[arg(long)]
+    l2_rpc: Option<String>,
+
     #[arg(long)]
     chain_id: u64,
 
@@ -183,8 +102,6 @@ async fn main() {
     let server = args
         .server_url
         .unwrap_or_else(|| "http://localhost:8545".to_string());
-    println!("Diamond Proxy: {}", args.address);
-
     let signer: PrivateKeySigner = args.governance_private_key.parse().unwrap();
     let signer_address = signer.address();
 
@@ -194,7 +111,16 @@ async fn main() {
         .await
         .unwrap();
 
-    let address = Address::from_hex(args.address).unwrap();
+    let address = resolve_diamond_proxy(
+        &provider,
+        args.address.as_deref(),
+        args.bridgehub.as_deref(),
+        Some(args.chain_id),
+        args.l2_rpc.as_deref(),
+    )
+    .await
+    .unwrap();
+    println!("Diamond Proxy: {}", address);
 
     let contract = IHyperchain::new(address, provider.clone());
 
@@ -3,6 +3,7 @@ use bellman::{bn256::Bn256, plonk::better_better_cs::proof::Proof as PlonkProof}
 use circuit_definitions::circuit_definitions::aux_layer::ZkSyncSnarkWrapperCircuit;
 
 use clap::{Parser, Subcommand};
+use common::DEFAULT_INDEX_DIR;
 
 use serde_json::Value;
 use std::error::Error;
@@ -55,6 +56,9 @@ enum Command {
         sequencer_rpc: String,
         #[arg(long)]
         sequencer_prover_api: String,
+        /// Directory with the index of already fetched batches.
+        #[arg(long, default_value = DEFAULT_INDEX_DIR)]
+        index_dir: String,
     },
 }
 
@@ -76,6 +80,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
             l1_rpc,
             sequencer_rpc,
             sequencer_prover_api,
+            index_dir,
         } => {
             run_ohbender(
                 binary,
@@ -84,6 +89,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 l1_rpc,
                 sequencer_rpc,
                 sequencer_prover_api,
+                index_dir,
             )
             .await
         }
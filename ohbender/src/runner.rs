@@ -1,10 +1,14 @@
 use std::error::Error;
 
-use alloy::primitives::{Address, U256};
-use alloy::{hex::FromHex, providers::ProviderBuilder, sol};
+use alloy::primitives::Address;
+use alloy::providers::ProviderBuilder;
 use bellman::{bn256::Bn256, plonk::better_better_cs::proof::Proof as PlonkProof};
 use circuit_definitions::circuit_definitions::aux_layer::ZkSyncSnarkWrapperCircuit;
 use cli::prover_utils::create_final_proofs_from_program_proof;
+use common::{
+    IHyperchain, check_verification_key_hash, get_bridgehub, get_chain_id, get_diamond_proxy,
+    snark_vk_hash,
+};
 use zkos_wrapper::{prove_fri_risc_wrapper, prove_risc_wrapper_with_snark};
 
 use crate::batches::{
@@ -21,12 +25,14 @@ pub async fn run_ohbender(
     l1_rpc: String,
     sequencer_rpc: String,
     sequencer_prover_api: String,
+    index_dir: String,
 ) -> Result<(), Box<dyn Error>> {
     // First - let's call the sequencer_rpc 'zks_getBridgehubContract' JSON RPC to get the bridgehub address.
 
     let bridgehub = get_bridgehub(&sequencer_rpc).await?;
     let chain_id = get_chain_id(&sequencer_rpc).await?;
-    let diamond_proxy = get_diamond_proxy(&l1_rpc, &bridgehub, chain_id).await?;
+    let l1_provider = ProviderBuilder::new().connect(&l1_rpc).await?;
+    let diamond_proxy = get_diamond_proxy(&l1_provider, bridgehub, chain_id).await?;
 
     println!("Bridgehub address: {}", bridgehub);
     println!("Chain ID: {}", chain_id);
@@ -46,7 +52,8 @@ pub async fn run_ohbender(
         let provider = ProviderBuilder::new().connect(&l1_rpc).await.unwrap();
 
         let batches =
-            fetch_commit_hashes_up_to_batch(&provider, diamond_proxy, start_batch - 1).await?;
+            fetch_commit_hashes_up_to_batch(&provider, diamond_proxy, start_batch - 1, &index_dir)
+                .await?;
 
         let proofs = fetch_fri_proofs(sequencer_prover_api, start_batch, end_batch).await?;
         println!("Fetched {} proofs - now merging", proofs.len());
@@ -88,6 +95,15 @@ pub async fn run_ohbender(
             serde_json::to_string_pretty(&snark_vk)?,
         )?;
 
+        // Don't send the proof if the verifier on L1 uses a different key.
+        check_verification_key_hash(
+            &provider,
+            diamond_proxy,
+            PLONK_VERIFIER_TYPE,
+            snark_vk_hash(&snark_vk)?,
+        )
+        .await?;
+
         let codegen_snark_proof: PlonkProof<Bn256, ZkSyncSnarkWrapperCircuit> =
             serde_json::from_str(&serialized_snark_proof)?;
         let (_, serialized_proof) = crypto_codegen::serialize_proof(&codegen_snark_proof);
@@ -119,84 +135,8 @@ pub async fn run_ohbender(
     Ok(())
 }
 
-sol! {
-    #[sol(rpc)]
-    contract IBridgehub {
-        function getZKChain(uint256 _chainId) external view returns (address);
-    }
-    #[sol(rpc)]
-    contract IHyperchain {
-        function getTotalBatchesCommitted() external view returns (uint256);
-        function getTotalBatchesVerified() external view returns (uint256);
-        function getTotalBatchesExecuted() external view returns (uint256);
-    }
-}
-
-pub async fn get_bridgehub(sequencer_rpc: &String) -> Result<String, Box<dyn Error>> {
-    let client = reqwest::Client::new();
-    let request_body = serde_json::json!({
-        "jsonrpc": "2.0",
-        "method": "zks_getBridgehubContract",
-        "params": [],
-        "id": 1,
-    });
-
-    let response = client
-        .post(sequencer_rpc)
-        .json(&request_body)
-        .send()
-        .await?
-        .error_for_status()?;
-
-    let response_json: serde_json::Value = response.json().await?;
-
-    Ok(response_json["result"]
-        .as_str()
-        .ok_or("Failed to parse bridgehub address")?
-        .to_string())
-}
-
-pub async fn get_chain_id(sequencer_rpc: &String) -> Result<u64, Box<dyn Error>> {
-    let client = reqwest::Client::new();
-    let request_body = serde_json::json!({
-        "jsonrpc": "2.0",
-        "method": "eth_chainId",
-        "params": [],
-        "id": 1,
-    });
-
-    let response = client
-        .post(sequencer_rpc)
-        .json(&request_body)
-        .send()
-        .await?
-        .error_for_status()?;
-
-    let response_json: serde_json::Value = response.json().await?;
-
-    let chain_id_hex = response_json["result"]
-        .as_str()
-        .ok_or("Failed to parse chain id")?;
-    let chain_id = u64::from_str_radix(chain_id_hex.trim_start_matches("0x"), 16)?;
-    Ok(chain_id)
-}
-
-pub async fn get_diamond_proxy(
-    l1_rpc: &String,
-    bridgehub: &String,
-    chain_id: u64,
-) -> Result<Address, Box<dyn Error>> {
-    let provider = ProviderBuilder::new().connect(l1_rpc).await.unwrap();
-
-    let address = Address::from_hex(bridgehub).unwrap();
-
-    let bridgehub = IBridgehub::new(address, provider.clone());
-
-    let diamond_proxy = bridgehub.getZKChain(U256::from(chain_id)).call().await?;
-    println!("Diamond Proxy address: {}", diamond_proxy);
-
-    Ok(diamond_proxy)
-}
+/// Ohbender proofs are checked by the PLONK verifier of DualVerifier - so its key is the one to compare.
+const PLONK_VERIFIER_TYPE: u64 = 1;
 
 #[derive(Debug)]
 pub struct BatchInfo {
@@ -0,0 +1,98 @@
+// Batches exported to a file - so that they can be used without access to L1 (air-gapped signing,
+// or reproducing a bug with exactly the same data).
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fs,
+};
+
+use alloy::primitives::Address;
+use common::{CommitBoojumOSBatchInfo, StoredBatchInfo};
+use serde::{Deserialize, Serialize};
+
+/// Bump whenever the format of the file changes - older files are then rejected.
+const BATCHES_FILE_VERSION: u32 = 2;
+
+/// Commit info (Boojum OS only) and stored info of the batches, by batch number.
+pub type Batches = (
+    HashMap<u64, CommitBoojumOSBatchInfo>,
+    HashMap<u64, StoredBatchInfo>,
+);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchesFile {
+    pub version: u32,
+    pub l1_chain_id: u64,
+    pub diamond_proxy: Address,
+    /// Only for Boojum OS chains (for Era chains there is just the `stored` info).
+    pub commits: BTreeMap<u64, CommitBoojumOSBatchInfo>,
+    pub stored: BTreeMap<u64, StoredBatchInfo>,
+}
+
+impl BatchesFile {
+    pub fn new(
+        l1_chain_id: u64,
+        diamond_proxy: Address,
+        commits: &HashMap<u64, CommitBoojumOSBatchInfo>,
+        stored: &HashMap<u64, StoredBatchInfo>,
+    ) -> Self {
+        Self {
+            version: BATCHES_FILE_VERSION,
+            l1_chain_id,
+            diamond_proxy,
+            commits: commits.iter().map(|(k, v)| (*k, v.clone())).collect(),
+            stored: stored.iter().map(|(k, v)| (*k, v.clone())).collect(),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Loads the batches of a given diamond proxy - fails if the file is for another one (or another L1,
+    /// if `l1_chain_id` is given, or has another version).
+    pub fn load(
+        path: &str,
+        l1_chain_id: Option<u64>,
+        diamond_proxy: Address,
+    ) -> Result<Batches, Box<dyn Error>> {
+        let file: BatchesFile = serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|err| format!("Invalid batches file {}: {}", path, err))?;
+        if file.version != BATCHES_FILE_VERSION {
+            return Err(format!(
+                "Batches file {} has version {}, but {} is supported",
+                path, file.version, BATCHES_FILE_VERSION
+            )
+            .into());
+        }
+        match l1_chain_id {
+            Some(l1_chain_id) if l1_chain_id != file.l1_chain_id => {
+                return Err(format!(
+                    "Batches file {} is for L1 chain {}, not {}",
+                    path, file.l1_chain_id, l1_chain_id
+                )
+                .into());
+            }
+            Some(_) => {}
+            None => eprintln!(
+                "Batches file {} is for L1 chain {} - pass --l1-chain-id to check it",
+                path, file.l1_chain_id
+            ),
+        }
+        if file.diamond_proxy != diamond_proxy {
+            return Err(format!(
+                "Batches file {} is for diamond proxy {}, not {}",
+                path, file.diamond_proxy, diamond_proxy
+            )
+            .into());
+        }
+        eprintln!("Loaded {} batches from {}", file.stored.len(), path);
+        Ok((
+            file.commits.into_iter().collect(),
+            file.stored.into_iter().collect(),
+        ))
+    }
+}
@@ -1,139 +1,108 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, error::Error};
 
-use alloy::{hex::FromHex, primitives::B256, providers::Provider, sol_types::SolCall};
-use reqwest::Client;
-use serde_json::Value;
+use alloy::{
+    providers::{Provider, WalletProvider},
+    sol_types::SolCall,
+};
 
-use crate::{
+use common::{
     IHyperchain::{self, IHyperchainInstance},
-    StoredBatchInfo,
-    l1_merkle::MerkleInfoForExecute,
+    StoredBatchInfo, describe_call_error,
 };
 
-pub async fn get_l1_tx_for_block(l2_sequencer: &str, block: u64) -> Vec<String> {
-    let client = Client::new();
-
-    // First, get block hash from block number.
-    let block_number_hex = format!("0x{:x}", block);
-
-    let transaction_hashes = {
-        let req_body = serde_json::json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "eth_getBlockByNumber",
-            "params": [block_number_hex, false]
-        });
-        let resp = client
-            .post(l2_sequencer)
-            .json(&req_body)
-            .send()
-            .await
-            .expect("Failed to send request");
-        let json: Value = resp.json().await.expect("Invalid JSON");
-
-        json["result"]["transactions"]
-            .as_array()
-            .expect("Transactions not found")
-            .iter()
-            .map(|x| x.as_str().unwrap().to_string())
-            .collect::<Vec<_>>()
-    };
-
-    let transactions = {
-        let req_body = serde_json::json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "eth_getBlockByNumber",
-            "params": [block_number_hex, true]
-        });
-        let resp = client
-            .post(l2_sequencer)
-            .json(&req_body)
-            .send()
-            .await
-            .expect("Failed to send request");
-        let json: Value = resp.json().await.expect("Invalid JSON");
-
-        json["result"]["transactions"]
-            .as_array()
-            .expect("Transactions not found")
-            .clone()
-    };
-
-    assert_eq!(transaction_hashes.len(), transactions.len());
-
-    let l1_hashes = transaction_hashes
-        .iter()
-        .zip(transactions.iter())
-        .filter_map(|(tx_hash, tx)| {
-            if tx["type"].as_str() == Some("0x2a") {
-                Some(tx_hash.clone())
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<_>>();
-
-    l1_hashes
-}
+use crate::{
+    fees::FeePolicy,
+    l1_merkle::MerkleInfoForExecute,
+    output::{TxAction, TxResult},
+    priority_ops::{PriorityOps, PriorityRequests, validate_priority_tree},
+};
 
-pub async fn execute_batches<P: Provider + Clone>(
+pub async fn execute_batches<P: Provider + WalletProvider + Clone>(
     contract: IHyperchainInstance<P>,
     start: u64,
     end: u64,
-    l2_sequencer: &str,
+    priority_ops: &PriorityOps,
     stored: &HashMap<u64, StoredBatchInfo>,
+    fees: &FeePolicy,
     dry_run: bool,
-) {
+) -> Result<TxResult, Box<dyn Error>> {
     // Execute start
     let mut execute_data = vec![0u8];
 
     let new_batches = (start..=end)
-        .map(|x| stored.get(&x).unwrap().clone())
-        .collect();
-
-    let mut l1_tx_map = HashMap::new();
-
-    // Actually start from block 1.
-    for block in 1..=end {
-        let l1_txs = get_l1_tx_for_block(l2_sequencer, block.into()).await;
-        let txs = l1_txs
-            .iter()
-            .map(|tx_hash| B256::from_hex(tx_hash).expect("Invalid L1 transaction hash"))
-            .collect::<Vec<B256>>();
-        l1_tx_map.insert(block, txs);
-    }
+        .map(|x| {
+            stored
+                .get(&x)
+                .cloned()
+                .ok_or_else(|| format!("Batch {} not found", x))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-    let merkle_info = MerkleInfoForExecute::init(&l1_tx_map);
+    // Shared by the fetch and the validation, so that L1 is scanned for each priority request only once.
+    let mut requests = PriorityRequests::default();
+    let l1_tx_map = priority_ops
+        .fetch(&contract, start, end, stored, &mut requests)
+        .await?;
 
-    let priority_ops = (start..=end)
-        .map(|x| {
-            let batch = stored.get(&x).unwrap();
-            let batch_l1_txs: u64 = batch.numberOfLayer1Txs.try_into().unwrap();
-            println!("Batch {} l1txs: {}", x, batch.numberOfLayer1Txs);
-            println!("priority op hash: {}", batch.priorityOperationsHash);
-            let item_hashes = l1_tx_map.get(&x).unwrap().clone();
-            assert_eq!(item_hashes.len() as u64, batch_l1_txs);
+    let start_index: u64 = contract
+        .getPriorityTreeStartIndex()
+        .call()
+        .await?
+        .try_into()?;
+    validate_priority_tree(
+        &contract,
+        &l1_tx_map,
+        start,
+        end,
+        stored,
+        start_index,
+        &mut requests,
+    )
+    .await?;
+
+    let mut merkle_info = MerkleInfoForExecute::init(&l1_tx_map, start_index);
+    // The operations of the executed batches are not needed for the paths.
+    merkle_info.trim_before_block(start);
+
+    let priority_ops_info = (start..=end)
+        .map(|x| -> Result<_, Box<dyn Error>> {
+            let batch = &stored[&x];
+            let batch_l1_txs: u64 = batch.numberOfLayer1Txs.try_into()?;
+            eprintln!("Batch {} l1txs: {}", x, batch.numberOfLayer1Txs);
+            eprintln!("priority op hash: {}", batch.priorityOperationsHash);
+            let item_hashes = l1_tx_map
+                .get(&x)
+                .cloned()
+                .ok_or_else(|| format!("No priority operations for batch {}", x))?;
+            if item_hashes.len() as u64 != batch_l1_txs {
+                return Err(format!(
+                    "Batch {} has {} priority operations, but {} were found",
+                    x,
+                    batch_l1_txs,
+                    item_hashes.len()
+                )
+                .into());
+            }
 
             let (root, left_path, right_path) =
-                merkle_info.get_merkle_path_for_l1_tx_in_block(x).clone();
-            println!("Merkle root: {}", root);
-            println!("Left path: {:?}", left_path);
-            println!("Right path: {:?}", right_path);
+                merkle_info.get_merkle_path_for_l1_tx_in_block(x)?;
+            eprintln!("Merkle root: {}", root);
+            eprintln!("Left path: {:?}", left_path);
+            eprintln!("Right path: {:?}", right_path);
 
             // Number of item hashes must match number of l1tx in a given batch.
             //let item_hashes = vec![FixedBytes::ZERO; batch.numberOfLayer1Txs.try_into().unwrap()];
-            IHyperchain::PriorityOpsBatchInfo {
+            Ok(IHyperchain::PriorityOpsBatchInfo {
                 leftPath: left_path,
                 rightPath: right_path,
                 itemHashes: item_hashes,
-            }
+            })
         })
-        .collect();
+        .collect::<Result<Vec<_>, _>>()?;
 
     let proof_payload = IHyperchain::executePayloadCall {
         executeData: new_batches,
-        priorityOps: priority_ops,
+        priorityOps: priority_ops_info,
     };
 
     proof_payload.abi_encode_raw(&mut execute_data);
@@ -147,9 +116,9 @@ pub async fn execute_batches<P: Provider + Clone>(
         )
         .call()
         .await
-        .unwrap();
+        .map_err(|err| format!("Execute call failed: {}", describe_call_error(&err)))?;
 
-    if dry_run == false {
+    if !dry_run {
         let tx = contract
             .executeBatchesSharedBridge(
                 0.try_into().unwrap(),
@@ -157,21 +126,16 @@ pub async fn execute_batches<P: Provider + Clone>(
                 end.try_into().unwrap(),
                 execute_data.into(),
             )
-            .max_priority_fee_per_gas(2_000_000_002)
-            .max_fee_per_gas(2_000_000_002)
-            .send()
-            .await
-            .unwrap();
-
-        println!("Transaction sent: {}", tx.tx_hash());
-        let receipt = tx.get_receipt().await.unwrap();
-        if receipt.status() {
-            println!("Transaction succeeded: {:?}", receipt);
-        } else {
-            println!("Transaction failed: {:?}", receipt);
-        }
+            .into_transaction_request();
+        let receipt = fees.send_with_replacement(contract.provider(), tx).await?;
+        eprintln!("Transaction receipt: {:?}", receipt);
+        Ok(TxResult::from_receipt(
+            TxAction::Execute,
+            start,
+            end,
+            &receipt,
+        ))
     } else {
-        println!("\x1b[32mExecute call was successful.\x1b[0m");
-        println!("\x1b[31mThis was just a dry-run\x1b[0m");
+        Ok(TxResult::dry_run(TxAction::Execute, start, end))
     }
 }
@@ -0,0 +1,210 @@
+// Fees for the prove & execute transactions, and replacing the ones that got stuck.
+
+use std::{
+    error::Error,
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    network::TransactionBuilder,
+    providers::{Provider, WalletProvider},
+    rpc::types::{TransactionReceipt, TransactionRequest},
+};
+use clap::{Args, ValueEnum};
+
+/// How often we check whether any of the sent transactions got mined.
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FeeMode {
+    /// Use the fees estimated by the node.
+    Estimate,
+    /// Use --max-fee-per-gas and --max-priority-fee-per-gas as they are (they are never bumped).
+    Fixed,
+    /// Multiply the estimated fees by --fee-multiplier, but never go above --max-fee-per-gas.
+    Capped,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct FeePolicy {
+    /// How to pick the EIP-1559 fees.
+    #[arg(long, value_enum, default_value_t = FeeMode::Estimate)]
+    pub fee_mode: FeeMode,
+    /// Max fee per gas (in wei) - the fee for 'fixed' mode, and the cap for 'capped' mode.
+    #[arg(long)]
+    pub max_fee_per_gas: Option<u128>,
+    /// Max priority fee per gas (in wei) - the fee for 'fixed' mode, and the cap for 'capped' mode.
+    #[arg(long)]
+    pub max_priority_fee_per_gas: Option<u128>,
+    /// Multiplier for the estimated fees (only for 'capped' mode).
+    #[arg(long, default_value_t = 1.5)]
+    pub fee_multiplier: f64,
+    /// If the transaction is not mined within this many seconds, it is re-sent with bumped fees.
+    #[arg(long, default_value_t = 180)]
+    pub tx_timeout: u64,
+    /// By how much (in percent) to bump the fees of the replacement transaction.
+    /// Nodes usually reject replacements with less than 10%.
+    #[arg(long, default_value_t = 20)]
+    pub fee_bump_percent: u128,
+    /// How many times the transaction can be replaced, before we give up.
+    #[arg(long, default_value_t = 5)]
+    pub max_replacements: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fees {
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+}
+
+impl FeePolicy {
+    /// Fees for the first attempt.
+    pub async fn initial_fees<P: Provider>(&self, provider: &P) -> Result<Fees, Box<dyn Error>> {
+        let fees = match self.fee_mode {
+            FeeMode::Estimate => {
+                let estimation = provider.estimate_eip1559_fees().await?;
+                Fees {
+                    max_fee_per_gas: estimation.max_fee_per_gas,
+                    max_priority_fee_per_gas: estimation.max_priority_fee_per_gas,
+                }
+            }
+            FeeMode::Fixed => Fees {
+                max_fee_per_gas: self
+                    .max_fee_per_gas
+                    .ok_or("--max-fee-per-gas is required in 'fixed' fee mode")?,
+                max_priority_fee_per_gas: self
+                    .max_priority_fee_per_gas
+                    .ok_or("--max-priority-fee-per-gas is required in 'fixed' fee mode")?,
+            },
+            FeeMode::Capped => {
+                if self.max_fee_per_gas.is_none() {
+                    return Err("--max-fee-per-gas is required in 'capped' fee mode".into());
+                }
+                let estimation = provider.estimate_eip1559_fees().await?;
+                self.cap(Fees {
+                    max_fee_per_gas: multiply(estimation.max_fee_per_gas, self.fee_multiplier),
+                    max_priority_fee_per_gas: multiply(
+                        estimation.max_priority_fee_per_gas,
+                        self.fee_multiplier,
+                    ),
+                })
+            }
+        };
+        Ok(fees)
+    }
+
+    /// Fees for the replacement transaction - or None if we cannot go any higher.
+    pub fn bumped_fees(&self, fees: Fees) -> Option<Fees> {
+        let bump = |fee: u128| fee * (100 + self.fee_bump_percent) / 100 + 1;
+        let bumped = self.cap(Fees {
+            max_fee_per_gas: bump(fees.max_fee_per_gas),
+            max_priority_fee_per_gas: bump(fees.max_priority_fee_per_gas),
+        });
+        // Replacement that is not strictly more expensive would be rejected by the node anyway.
+        (bumped.max_fee_per_gas > fees.max_fee_per_gas
+            && bumped.max_priority_fee_per_gas > fees.max_priority_fee_per_gas)
+            .then_some(bumped)
+    }
+
+    /// In 'fixed' mode the fixed fees are the cap as well - so they are never bumped.
+    fn cap(&self, fees: Fees) -> Fees {
+        if self.fee_mode == FeeMode::Estimate {
+            return fees;
+        }
+        let max_fee_per_gas = fees
+            .max_fee_per_gas
+            .min(self.max_fee_per_gas.unwrap_or(u128::MAX));
+        let max_priority_fee_per_gas = fees
+            .max_priority_fee_per_gas
+            .min(self.max_priority_fee_per_gas.unwrap_or(u128::MAX))
+            .min(max_fee_per_gas);
+        Fees {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        }
+    }
+
+    /// Sends the transaction, and waits for it to be mined.
+    /// If it takes longer than the timeout, re-sends it with the same nonce and bumped fees.
+    pub async fn send_with_replacement<P: Provider + WalletProvider>(
+        &self,
+        provider: &P,
+        tx: TransactionRequest,
+    ) -> Result<TransactionReceipt, Box<dyn Error>> {
+        let from = provider.default_signer_address();
+        let nonce = provider.get_transaction_count(from).pending().await?;
+        let mut fees = self.initial_fees(provider).await?;
+        let mut sent = vec![];
+        let mut replacements = 0;
+        let mut send = true;
+
+        loop {
+            if send {
+                let tx = tx
+                    .clone()
+                    .with_from(from)
+                    .with_nonce(nonce)
+                    .with_max_fee_per_gas(fees.max_fee_per_gas)
+                    .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+                match provider.send_transaction(tx).await {
+                    Ok(pending) => {
+                        eprintln!(
+                            "Transaction sent: {} (nonce {}, max fee {}, priority fee {})",
+                            pending.tx_hash(),
+                            nonce,
+                            fees.max_fee_per_gas,
+                            fees.max_priority_fee_per_gas
+                        );
+                        sent.push(*pending.tx_hash());
+                    }
+                    // Most likely one of the previous transactions was mined in the meantime.
+                    Err(err) if !sent.is_empty() => {
+                        eprintln!("Failed to send the replacement transaction: {}", err);
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+
+            let deadline = Instant::now() + Duration::from_secs(self.tx_timeout);
+            while Instant::now() < deadline {
+                // Any of the transactions with this nonce can be the one that got mined.
+                for tx_hash in &sent {
+                    if let Some(receipt) = provider.get_transaction_receipt(*tx_hash).await? {
+                        return Ok(receipt);
+                    }
+                }
+                tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+            }
+
+            if replacements >= self.max_replacements {
+                return Err(format!(
+                    "Transaction with nonce {} was not mined after {} replacements (sent: {:?})",
+                    nonce, replacements, sent
+                )
+                .into());
+            }
+            match self.bumped_fees(fees) {
+                Some(bumped) => {
+                    eprintln!(
+                        "Transaction not mined within {}s - replacing it with bumped fees",
+                        self.tx_timeout
+                    );
+                    fees = bumped;
+                    replacements += 1;
+                    send = true;
+                }
+                None => {
+                    eprintln!(
+                        "Transaction not mined within {}s, but the fees are already at the cap - still waiting",
+                        self.tx_timeout
+                    );
+                    send = false;
+                }
+            }
+        }
+    }
+}
+
+fn multiply(fee: u128, multiplier: f64) -> u128 {
+    (fee as f64 * multiplier) as u128
+}
@@ -0,0 +1,287 @@
+// Decoding of the commit / prove / execute transactions sent by someone else.
+
+use std::{error::Error, fmt};
+
+use alloy::{
+    consensus::Transaction,
+    primitives::{Address, B256, Bytes, U256},
+    providers::Provider,
+    sol_types::SolCall,
+};
+use serde::Serialize;
+
+use common::{
+    DecodedCommitData,
+    IHyperchain::{self, IHyperchainInstance},
+    ProtocolVersion, StoredBatchInfo,
+    commit_tx::IValidatorTimelock,
+    decode_commit_data,
+};
+
+use crate::output::{OutputFormat, print_json};
+
+/// Who was called - the diamond proxy itself (with the chain id), or ValidatorTimelock (with the chain address).
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Target {
+    ChainId(U256),
+    ChainAddress(Address),
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::ChainId(chain_id) => write!(f, "diamond proxy (chain id {})", chain_id),
+            Target::ChainAddress(address) => {
+                write!(f, "ValidatorTimelock (chain address {})", address)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "function", rename_all = "camelCase")]
+pub enum InspectedCall {
+    #[serde(rename_all = "camelCase")]
+    Commit {
+        target: Target,
+        process_from: U256,
+        process_to: U256,
+        encoding_version: u8,
+        commit_data: DecodedCommitData,
+    },
+    #[serde(rename_all = "camelCase")]
+    Prove {
+        target: Target,
+        process_from: U256,
+        process_to: U256,
+        encoding_version: u8,
+        old: StoredBatchInfo,
+        new_info: Vec<StoredBatchInfo>,
+        proof: Vec<U256>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Execute {
+        target: Target,
+        process_from: U256,
+        process_to: U256,
+        encoding_version: u8,
+        execute_data: Vec<StoredBatchInfo>,
+        priority_ops: Vec<IHyperchain::PriorityOpsBatchInfo>,
+    },
+}
+
+/// Fetches the transaction, and decodes its commit / prove / execute call (with the nested payload).
+pub async fn inspect_tx<P: Provider>(
+    contract: &IHyperchainInstance<P>,
+    tx_hash: B256,
+) -> Result<InspectedCall, Box<dyn Error>> {
+    let tx = contract
+        .provider()
+        .get_transaction_by_hash(tx_hash)
+        .await?
+        .ok_or("Transaction not found")?;
+    // Commit data is decoded with the protocol version at the time of the transaction.
+    let protocol_version = match tx.block_number {
+        Some(block_number) => ProtocolVersion::fetch_at(contract, block_number).await?,
+        None => ProtocolVersion::fetch(contract).await?,
+    };
+    decode_call(tx.input(), protocol_version)
+}
+
+pub fn decode_call(
+    input: &Bytes,
+    protocol_version: ProtocolVersion,
+) -> Result<InspectedCall, Box<dyn Error>> {
+    let selector = input.get(..4).ok_or("Calldata too short")?;
+
+    if selector == IHyperchain::commitBatchesSharedBridgeCall::SELECTOR {
+        let call = IHyperchain::commitBatchesSharedBridgeCall::abi_decode(input)?;
+        commit(
+            Target::ChainId(call._0),
+            call._processFrom,
+            call._processTo,
+            &call.commitData,
+            protocol_version,
+        )
+    } else if selector == IValidatorTimelock::commitBatchesSharedBridgeCall::SELECTOR {
+        let call = IValidatorTimelock::commitBatchesSharedBridgeCall::abi_decode(input)?;
+        commit(
+            Target::ChainAddress(call._chainAddress),
+            call._processFrom,
+            call._processTo,
+            &call._commitData,
+            protocol_version,
+        )
+    } else if selector == IHyperchain::proveBatchesSharedBridgeCall::SELECTOR {
+        let call = IHyperchain::proveBatchesSharedBridgeCall::abi_decode(input)?;
+        prove(
+            Target::ChainId(call._0),
+            call._processBatchFrom,
+            call._processBatchTo,
+            &call._proofData,
+        )
+    } else if selector == IValidatorTimelock::proveBatchesSharedBridgeCall::SELECTOR {
+        let call = IValidatorTimelock::proveBatchesSharedBridgeCall::abi_decode(input)?;
+        prove(
+            Target::ChainAddress(call._chainAddress),
+            call._processBatchFrom,
+            call._processBatchTo,
+            &call._proofData,
+        )
+    } else if selector == IHyperchain::executeBatchesSharedBridgeCall::SELECTOR {
+        let call = IHyperchain::executeBatchesSharedBridgeCall::abi_decode(input)?;
+        execute(
+            Target::ChainId(call._0),
+            call._processFrom,
+            call._processTo,
+            &call._executeData,
+        )
+    } else if selector == IValidatorTimelock::executeBatchesSharedBridgeCall::SELECTOR {
+        let call = IValidatorTimelock::executeBatchesSharedBridgeCall::abi_decode(input)?;
+        execute(
+            Target::ChainAddress(call._chainAddress),
+            call._processFrom,
+            call._processTo,
+            &call._executeData,
+        )
+    } else {
+        Err(format!(
+            "Unknown selector 0x{} - not a commit, prove or execute call",
+            alloy::hex::encode(selector)
+        )
+        .into())
+    }
+}
+
+fn commit(
+    target: Target,
+    process_from: U256,
+    process_to: U256,
+    data: &[u8],
+    protocol_version: ProtocolVersion,
+) -> Result<InspectedCall, Box<dyn Error>> {
+    Ok(InspectedCall::Commit {
+        target,
+        process_from,
+        process_to,
+        encoding_version: *data.first().ok_or("Empty commit data")?,
+        commit_data: decode_commit_data(data, protocol_version)?,
+    })
+}
+
+fn prove(
+    target: Target,
+    process_from: U256,
+    process_to: U256,
+    data: &[u8],
+) -> Result<InspectedCall, Box<dyn Error>> {
+    let (&encoding_version, payload) = data.split_first().ok_or("Empty proof data")?;
+    let payload = IHyperchain::proofPayloadCall::abi_decode_raw(payload)?;
+    Ok(InspectedCall::Prove {
+        target,
+        process_from,
+        process_to,
+        encoding_version,
+        old: payload.old,
+        new_info: payload.newInfo,
+        proof: payload.proof,
+    })
+}
+
+fn execute(
+    target: Target,
+    process_from: U256,
+    process_to: U256,
+    data: &[u8],
+) -> Result<InspectedCall, Box<dyn Error>> {
+    let (&encoding_version, payload) = data.split_first().ok_or("Empty execute data")?;
+    let payload = IHyperchain::executePayloadCall::abi_decode_raw(payload)?;
+    Ok(InspectedCall::Execute {
+        target,
+        process_from,
+        process_to,
+        encoding_version,
+        execute_data: payload.executeData,
+        priority_ops: payload.priorityOps,
+    })
+}
+
+pub fn print_inspected(call: &InspectedCall, format: OutputFormat) {
+    if format == OutputFormat::Json {
+        print_json(call);
+        return;
+    }
+    match call {
+        InspectedCall::Commit {
+            target,
+            process_from,
+            process_to,
+            encoding_version,
+            commit_data,
+        } => {
+            println!(
+                "Commit batches {}-{} on {}",
+                process_from, process_to, target
+            );
+            println!("Encoding version: {}", encoding_version);
+            match commit_data {
+                DecodedCommitData::BoojumOs { stored, commits } => {
+                    println!("Layout: Boojum OS");
+                    println!("Previous batch: {:#?}", stored);
+                    for commit in commits {
+                        println!("Batch {}: {:#?}", commit.batchNumber, commit);
+                    }
+                }
+                DecodedCommitData::Era { stored, commits } => {
+                    println!("Layout: Era");
+                    println!("Previous batch: {:#?}", stored);
+                    for commit in commits {
+                        println!("Batch {}: {:#?}", commit.batchNumber, commit);
+                    }
+                }
+            }
+        }
+        InspectedCall::Prove {
+            target,
+            process_from,
+            process_to,
+            encoding_version,
+            old,
+            new_info,
+            proof,
+        } => {
+            println!(
+                "Prove batches {}-{} on {}",
+                process_from, process_to, target
+            );
+            println!("Encoding version: {}", encoding_version);
+            println!("Previous batch: {:#?}", old);
+            for batch in new_info {
+                println!("Batch {}: {:#?}", batch.batchNumber, batch);
+            }
+            println!("Proof ({} elements):", proof.len());
+            for (i, element) in proof.iter().enumerate() {
+                println!("  [{}] {:#x}", i, element);
+            }
+        }
+        InspectedCall::Execute {
+            target,
+            process_from,
+            process_to,
+            encoding_version,
+            execute_data,
+            priority_ops,
+        } => {
+            println!(
+                "Execute batches {}-{} on {}",
+                process_from, process_to, target
+            );
+            println!("Encoding version: {}", encoding_version);
+            for (batch, priority_ops) in execute_data.iter().zip(priority_ops) {
+                println!("Batch {}: {:#?}", batch.batchNumber, batch);
+                println!("Priority ops: {:#?}", priority_ops);
+            }
+        }
+    }
+}
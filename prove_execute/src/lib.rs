@@ -0,0 +1,15 @@
+// Everything but the command line parsing (which is in main.rs) - a library, so that it can be tested.
+
+pub mod batches_file;
+pub mod execute;
+pub mod fees;
+pub mod inspect;
+pub mod l1_merkle;
+pub mod output;
+pub mod priority_ops;
+pub mod priority_proof;
+pub mod prove;
+pub mod revert;
+pub mod sequencer;
+pub mod snark;
+pub mod watch;
@@ -1,210 +1,36 @@
-use std::collections::{HashMap, HashSet};
+use std::{error::Error, process, time::Duration};
 
 use alloy::{
-    primitives::{Address, B256, U256, keccak256},
+    primitives::{Address, B256, U256},
     signers::local::PrivateKeySigner,
 };
 use clap::{Parser, Subcommand};
 
 use alloy::{
-    consensus::Transaction,
     hex::FromHex,
     providers::{Provider, ProviderBuilder},
-    rpc::types::Filter,
-    sol,
-    sol_types::{SolCall, SolEvent},
+};
+use common::{
+    DEFAULT_INDEX_DIR, IHyperchain, check_batch_range, get_batch_public_input,
+    resolve_diamond_proxy, shift_b256_right, snark_public_input_for_range, sync_batch_index_from,
 };
 
-use crate::{
+use prove_execute::{
+    batches_file::{Batches, BatchesFile},
     execute::execute_batches,
-    prove::{fake_prove_batches, prove_batches},
+    fees::FeePolicy,
+    inspect::{inspect_tx, print_inspected},
+    output::{
+        BatchPublicInput, ExportedBatches, OutputFormat, ProveAndExecute, PublicInputs, Status,
+        print_json,
+    },
+    priority_ops::{PriorityOps, PriorityOpsArgs, PriorityOpsSource},
+    priority_proof::priority_op_proof,
+    prove::{ProofType, fake_prove_batches, prove_batches},
+    revert::revert_batches,
+    watch::watch_batches,
 };
 
-mod execute;
-mod l1_merkle;
-mod prove;
-mod snark;
-
-sol! {
-    #[sol(rpc)]
-    contract IHyperchain {
-        function getVerifier() external view returns (address);
-        function getAdmin() external view returns (address);
-        function getTotalBatchesCommitted() external view returns (uint256);
-        function getTotalBatchesVerified() external view returns (uint256);
-        function getTotalBatchesExecuted() external view returns (uint256);
-        function getSemverProtocolVersion() external view returns (uint32, uint32, uint32);
-
-        function getL2BootloaderBytecodeHash() external view returns (bytes32);
-        function getL2DefaultAccountBytecodeHash() external view returns (bytes32);
-        function getL2SystemContractsUpgradeTxHash() external view returns (bytes32);
-        function getChainId() external view returns (uint256);
-        function getSettlementLayer() external view returns (address);
-
-        function getPriorityQueueSize() external view returns (uint256);
-        function getTotalPriorityTxs() external view returns (uint256);
-        function getPriorityTreeRoot() external view returns (bytes32);
-
-        function commitBatchesSharedBridge(uint256,uint256,uint256,bytes);
-
-        function tmpStuff(StoredBatchInfo stored, CommitBoojumOSBatchInfo[] commits) external;
-
-        function proofPayload(StoredBatchInfo old, StoredBatchInfo[] newInfo, uint256[] proof);
-
-        struct PriorityOpsBatchInfo {
-            bytes32[] leftPath;
-            bytes32[] rightPath;
-            bytes32[] itemHashes;
-        }
-
-        function executePayload(StoredBatchInfo[] executeData, PriorityOpsBatchInfo[] priorityOps);
-
-
-        function proveBatchesSharedBridge(
-            uint256, // _chainId
-            uint256 _processBatchFrom,
-            uint256 _processBatchTo,
-            bytes calldata _proofData
-        );
-
-        function executeBatchesSharedBridge(
-            uint256, // _chainId
-            uint256 _processFrom,
-            uint256 _processTo,
-            bytes calldata _executeData
-        );
-
-        event BlockCommit(uint256 indexed batchNumber, bytes32 indexed batchHash, bytes32 indexed commitment);
-
-    }
-    #[derive(Debug)]
-
-    struct StoredBatchInfo {
-        uint64 batchNumber;
-        bytes32 batchHash; // For Boojum OS batches we'll store here full state commitment
-        uint64 indexRepeatedStorageChanges; // For Boojum OS not used, 0
-        uint256 numberOfLayer1Txs;
-        bytes32 priorityOperationsHash;
-        bytes32 l2LogsTreeRoot;
-        uint256 timestamp; // For Boojum OS not used, 0
-        bytes32 commitment;// For Boojum OS batches we'll store batch output hash here
-    }
-    #[derive(Debug)]
-
-    struct CommitBoojumOSBatchInfo {
-        uint64 batchNumber;
-        // chain state commitment, this preimage is not opened on l1,
-        // it's guaranteed that this commitment commits to any state that needed for execution
-        // (state root, block number, bloch hahes)
-        bytes32 newStateCommitment;
-        // info about processed l1 txs, l2 to l1 logs and DA
-        uint256 numberOfLayer1Txs;
-        bytes32 priorityOperationsHash;
-        bytes32 l2LogsTreeRoot;
-        address l2DaValidator; // TODO: already saved in the storage, can just add from there to PI
-        bytes32 daCommitment;
-        // sending used batch inputs to validate on the settlement layer
-        uint64 firstBlockTimestamp;
-        uint64 lastBlockTimestamp;
-        uint256 chainId; // TODO: already saved in the storage, can just add from there to PI
-        // extra calldata to pass to da validator
-        bytes operatorDAInput;
-    }
-
-    #[derive(Debug)]
-
-    struct Merged {
-        StoredBatchInfo foo;
-        CommitBoojumOSBatchInfo[] bar;
-    }
-}
-
-pub fn compute_batch_outputs_hash(batch: &CommitBoojumOSBatchInfo) -> B256 {
-    let mut bytes = Vec::with_capacity(32 + 8 + 8 + 20 + 32 + 32 + 32 + 32 + 32);
-
-    // Encode chainId as 32-byte big-endian.
-    {
-        bytes.extend_from_slice(&batch.chainId.to_be_bytes::<32>());
-    }
-    // Encode firstBlockTimestamp (uint64 - 8 bytes)
-    bytes.extend_from_slice(&batch.firstBlockTimestamp.to_be_bytes());
-    // Encode lastBlockTimestamp (uint64 - 8 bytes)
-    bytes.extend_from_slice(&batch.lastBlockTimestamp.to_be_bytes());
-    // Encode l2DaValidator as 20 bytes already.
-    bytes.extend_from_slice(batch.l2DaValidator.as_slice());
-    // Encode daCommitment (bytes32 - 32 bytes)
-    bytes.extend_from_slice(batch.daCommitment.as_slice());
-    // Encode numberOfLayer1Txs as 32-byte big-endian.
-    {
-        bytes.extend_from_slice(&batch.numberOfLayer1Txs.to_be_bytes::<32>());
-    }
-    // Encode priorityOperationsHash (bytes32 - 32 bytes)
-    bytes.extend_from_slice(batch.priorityOperationsHash.as_slice());
-    // Encode l2LogsTreeRoot (bytes32 - 32 bytes)
-    bytes.extend_from_slice(batch.l2LogsTreeRoot.as_slice());
-    // Append zero upgrade tx hash (bytes32 - 32 bytes of zero)
-    bytes.extend_from_slice(&[0u8; 32]);
-
-    // Compute and return the keccak256 hash.
-    keccak256(&bytes).into()
-}
-
-pub fn commit_to_stored(info: CommitBoojumOSBatchInfo) -> StoredBatchInfo {
-    StoredBatchInfo {
-        batchNumber: info.batchNumber,
-        batchHash: info.newStateCommitment,
-        indexRepeatedStorageChanges: 0,
-        numberOfLayer1Txs: info.numberOfLayer1Txs,
-        priorityOperationsHash: info.priorityOperationsHash,
-        l2LogsTreeRoot: info.l2LogsTreeRoot,
-        timestamp: U256::from(0), // For Boojum OS not used, 0
-        commitment: compute_batch_outputs_hash(&info), // For Boojum OS batches we'll store batch output hash here
-    }
-}
-
-pub fn get_batch_public_input(prev_batch: &StoredBatchInfo, batch: &StoredBatchInfo) -> B256 {
-    let mut bytes = Vec::with_capacity(32 * 3);
-    bytes.extend_from_slice(prev_batch.batchHash.as_slice());
-    bytes.extend_from_slice(batch.batchHash.as_slice());
-    bytes.extend_from_slice(batch.commitment.as_slice());
-    keccak256(&bytes).into()
-}
-
-pub fn shift_b256_right(input: &B256) -> B256 {
-    let mut bytes = [0_u8; 32];
-    bytes[4..32].copy_from_slice(&input.as_slice()[0..28]);
-    B256::from_slice(&bytes)
-}
-
-pub fn snark_public_input_for_range(
-    batches: &HashMap<u64, StoredBatchInfo>,
-    start: u64,
-    end: u64,
-) -> B256 {
-    let mut result: Option<B256> = None;
-    for i in start..=end {
-        let batch = batches.get(&i).expect("Batch not found");
-        let prev_batch = batches.get(&(i - 1)).expect("Previous batch not found");
-        let public_input = get_batch_public_input(prev_batch, batch);
-        // Snark public input is public_input >> 32.
-        let snark_input = shift_b256_right(&public_input);
-
-        match result {
-            Some(ref mut res) => {
-                // Combine with previous result.
-                let mut combined = [0_u8; 64];
-                combined[..32].copy_from_slice(&res.0);
-                combined[32..].copy_from_slice(&snark_input.0);
-                *res = shift_b256_right(&keccak256(&combined));
-            }
-            None => {
-                result = Some(snark_input);
-            }
-        }
-    }
-    result.unwrap()
-}
-
 #[derive(Debug, Parser, Clone)]
 
 struct ArgsRange {
@@ -229,6 +55,45 @@ enum Command {
         /// Address of the L2 sequencer to use for execution.
         /// If not specified, it will use the local one.
         l2_sequencer: Option<String>,
+        #[clap(flatten)]
+        priority_ops: PriorityOpsArgs,
+    },
+    /// Decodes the commit, prove or execute call (with its payload) done by a given L1 transaction.
+    InspectTx { tx_hash: String },
+    /// Prints (as JSON) the Merkle proof that a priority operation with a given canonical tx hash is in the priority tree.
+    PriorityOpProof {
+        tx_hash: String,
+        #[arg(long)]
+        /// Address of the L2 sequencer to use.
+        /// If not specified, it will use the local one.
+        l2_sequencer: Option<String>,
+        #[clap(flatten)]
+        priority_ops: PriorityOpsArgs,
+    },
+    /// Writes the committed batches (stored and commit info) to a JSON file - to be used later with `--batches-file`.
+    ExportBatches {
+        #[arg(long)]
+        out: String,
+    },
+    /// Reverts the committed (but not executed) batches, so that `--to` becomes the last committed batch.
+    Revert {
+        #[arg(long)]
+        to: u64,
+    },
+    /// Keeps running, and fake-proves & executes the new batches as they get committed (until SIGINT).
+    Watch {
+        #[arg(long)]
+        /// Address of the L2 sequencer to use for execution.
+        /// If not specified, it will use the local one.
+        l2_sequencer: Option<String>,
+        #[clap(flatten)]
+        priority_ops: PriorityOpsArgs,
+        /// How often to check for the new batches (in seconds).
+        #[arg(long, default_value_t = 10)]
+        poll_interval: u64,
+        /// Max time to wait (in seconds) before retrying after an error.
+        #[arg(long, default_value_t = 300)]
+        max_backoff: u64,
     },
     /// Takes existing SNARK proof and submits it to the contract.
     Prove {
@@ -242,12 +107,26 @@ enum Command {
         /// range.start (useful if some other small proof was already submitted).
         #[arg(long)]
         snark_start: Option<u64>,
+
+        /// Type of the proof (the verifier that should check it).
+        #[arg(long, value_enum, default_value_t = ProofType::Ohbender)]
+        proof_type: ProofType,
+
+        /// Path to the SNARK verification key (snark.vk.json) - the key is compared with the one of the verifier on L1,
+        /// and PLONK and ohbender proofs are also verified with it locally (required, unless --skip-vk-check).
+        #[arg(long)]
+        vk_path: Option<String>,
+
+        /// Submit the proof without checking its verification key.
+        #[arg(long)]
+        skip_vk_check: bool,
     },
     /// Will use a 'fake verifier' (if supported) - this way it doesn't have to spend time creating snark proof.
     FakeProve {
-        /// Public input that shoudl be passed to the contract.
+        /// Public input that should be passed to the contract - computed from the batches if not specified
+        /// (if specified, it must match the computed one).
         #[arg(long)]
-        public_input: String,
+        public_input: Option<String>,
         #[clap(flatten)]
         range: ArgsRange,
     },
@@ -260,135 +139,116 @@ enum Command {
         /// Address of the L2 sequencer to use for execution.
         /// If not specified, it will use the local one.
         l2_sequencer: Option<String>,
+        #[clap(flatten)]
+        priority_ops: PriorityOpsArgs,
     },
 }
 
+impl Command {
+    /// Oldest batch that the command needs (the one before its range) - if it has a range.
+    fn first_batch(&self) -> Option<u64> {
+        let start = match self {
+            Command::PublicInput { range }
+            | Command::FakeProve { range, .. }
+            | Command::Execute { range, .. } => range.start,
+            Command::Prove {
+                range, snark_start, ..
+            } => snark_start.unwrap_or(range.start),
+            _ => return None,
+        };
+        Some(start.saturating_sub(1))
+    }
+}
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Command,
 
+    /// Address of the diamond proxy (instead, you can pass `--bridgehub` and `--chain-id`, or `--l2-rpc`).
     #[arg(short, long)]
-    address: String,
+    address: Option<String>,
     #[arg(short, long)]
     server_url: Option<String>,
 
+    /// Bridgehub on L1 - to find the diamond proxy of `--chain-id`.
     #[arg(long)]
-    private_key: Option<String>,
-}
-
-/// Iterates backwards over blocks in chunks and prints transactions that emit the given event.
-async fn iterate_blocks_for_event<P: Provider>(
-    provider: P,
-    contract_address: alloy::primitives::Address,
-    event_topic: B256,
-    chunk_size: u64,
-    num_blocks_to_scan: Option<u64>,
-) -> Result<HashSet<B256>, Box<dyn std::error::Error>> {
-    // Get the current block number.
-    let latest_block = provider.get_block_number().await?;
-    let mut current_block = latest_block;
-    let start_block = num_blocks_to_scan
-        .map(|x| current_block.saturating_sub(x))
-        .unwrap_or(0);
-
-    let mut result: HashSet<B256> = HashSet::new();
-
-    println!(
-        "Scanning blocks from {} to {}...",
-        start_block, current_block
-    );
-    // Loop backwards until block 0.
-    while current_block > start_block {
-        let from_block = current_block.saturating_sub(chunk_size) + 1;
-
-        let filter = Filter::new()
-            .from_block(from_block)
-            .to_block(current_block)
-            .address(contract_address)
-            .event_signature(event_topic);
-
-        // Get the matching logs.
-        let logs = provider.get_logs(&filter).await?;
-        for log in logs {
-            println!(
-                "Found event in tx: {:?} at block {:?}",
-                log.transaction_hash, log.block_number
-            );
-            result.insert(log.transaction_hash.unwrap());
-        }
-
-        if from_block == 1 {
-            break;
-        }
-        current_block = from_block - 1;
-    }
-    Ok(result)
-}
-
-/// Fetch batches that were sent in 'commit' transactions.
-pub async fn fetch_batches<P: Provider + Clone>(
-    provider: P,
-    diamond_proxy_address: alloy::primitives::Address,
-) -> (
-    HashMap<u64, CommitBoojumOSBatchInfo>,
-    HashMap<u64, StoredBatchInfo>,
-) {
-    let transactions = iterate_blocks_for_event(
-        provider.clone(),
-        diamond_proxy_address,
-        IHyperchain::BlockCommit::SIGNATURE_HASH,
-        10000,
-        // scan at most 1M blocks.
-        Some(1_000_000),
-    )
-    .await
-    .unwrap();
-
-    let mut batches = HashMap::new();
-    let mut stored = HashMap::new();
+    bridgehub: Option<String>,
+    #[arg(long)]
+    chain_id: Option<u64>,
+    /// L2 node - to find the diamond proxy through its bridgehub and chain id.
+    #[arg(long)]
+    l2_rpc: Option<String>,
 
-    for tx_hash in transactions {
-        let tx = provider.get_transaction_by_hash(tx_hash).await;
-        if tx.is_err() {
-            println!("Error fetching transaction {}", tx_hash);
-            continue;
-        }
+    #[arg(long)]
+    private_key: Option<String>,
 
-        let tx_data = tx.unwrap().expect("Transaction not found");
+    /// Directory with the index of already fetched batches.
+    #[arg(long, default_value = DEFAULT_INDEX_DIR)]
+    index_dir: String,
 
-        let tx = tx_data.inner.as_eip1559().unwrap().tx();
+    /// Take the batches from this file (created by `export-batches`) instead of fetching them from L1.
+    #[arg(long, global = true)]
+    batches_file: Option<String>,
 
-        if tx.input[..4] != IHyperchain::commitBatchesSharedBridgeCall::SELECTOR {
-            println!("Skipping transaction: {}", tx_hash);
-            continue;
-        }
+    /// L1 chain id that the `--batches-file` must be for (it is not asked from L1 - so that the file can be used offline).
+    #[arg(long, global = true)]
+    l1_chain_id: Option<u64>,
 
-        let decoded = IHyperchain::commitBatchesSharedBridgeCall::abi_decode(tx.input()).unwrap();
+    #[clap(flatten)]
+    fees: FeePolicy,
 
-        let commit_data = &decoded._3.clone()[1..];
+    /// Format of the result ('json' prints only the result to stdout - progress goes to stderr).
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, global = true)]
+    output: OutputFormat,
+}
 
-        let ww = IHyperchain::tmpStuffCall::abi_decode_raw(commit_data).unwrap();
-        for other in ww.commits {
-            batches.insert(other.batchNumber.clone(), other.clone());
-            stored.insert(other.batchNumber, commit_to_stored(other));
-        }
-        stored.insert(ww.stored.batchNumber, ww.stored);
-    }
-    (batches, stored)
+/// Fetch batches that were sent in 'commit' transactions (from `first_batch`, if given).
+/// Uses the on-disk index, so only the L1 blocks since the previous run are scanned.
+pub async fn fetch_batches<P: Provider>(
+    provider: &P,
+    diamond_proxy_address: Address,
+    index_dir: &str,
+    first_batch: Option<u64>,
+) -> Result<Batches, Box<dyn Error>> {
+    let index =
+        sync_batch_index_from(provider, diamond_proxy_address, index_dir, first_batch).await?;
+
+    Ok((
+        index.commits.into_iter().collect(),
+        index.stored.into_iter().collect(),
+    ))
 }
 
 #[tokio::main]
 async fn main() {
     let args = Cli::parse();
+    let output = args.output;
+    match run(args).await {
+        Ok(true) => {}
+        // Failed transaction - the result was already printed.
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            match output {
+                OutputFormat::Text => eprintln!("\x1b[31mError: {}\x1b[0m", err),
+                OutputFormat::Json => print_json(&serde_json::json!({ "error": err.to_string() })),
+            }
+            process::exit(1);
+        }
+    }
+}
+
+/// Runs the command and prints its result. Returns false if the transaction failed.
+async fn run(args: Cli) -> Result<bool, Box<dyn Error>> {
+    let output = args.output;
     let server = args
         .server_url
         .clone()
         .unwrap_or_else(|| "http://localhost:8545".to_string());
 
     let signer = if let Some(private_key) = args.private_key.clone() {
-        let signer: PrivateKeySigner = private_key.parse().unwrap();
+        let signer: PrivateKeySigner = private_key.parse()?;
         signer
     } else {
         PrivateKeySigner::random()
@@ -399,179 +259,326 @@ async fn main() {
     let provider = ProviderBuilder::new()
         .wallet(signer)
         .connect(&server)
-        .await
-        .unwrap();
-
-    let address = Address::from_hex(args.address.clone()).unwrap();
+        .await?;
+
+    let address = resolve_diamond_proxy(
+        &provider,
+        args.address.as_deref(),
+        args.bridgehub.as_deref(),
+        args.chain_id,
+        args.l2_rpc.as_deref(),
+    )
+    .await?;
 
     let contract = IHyperchain::new(address, provider.clone());
 
-    let (batches, stored) = fetch_batches(provider.clone(), address).await;
+    // These don't need any batches - so don't scan for them.
+    match &args.command {
+        Command::InspectTx { tx_hash } => {
+            let call = inspect_tx(&contract, B256::from_hex(tx_hash)?).await?;
+            print_inspected(&call, output);
+            return Ok(true);
+        }
+        Command::Revert { to } => {
+            let result =
+                revert_batches(contract, *to, &args.index_dir, &args.fees, dry_run).await?;
+            result.print(output);
+            return Ok(!result.is_failed());
+        }
+        _ => {}
+    }
+
+    let (batches, stored) = match &args.batches_file {
+        Some(path) => BatchesFile::load(path, args.l1_chain_id, address)?,
+        None => {
+            fetch_batches(
+                &provider,
+                address,
+                &args.index_dir,
+                args.command.first_batch(),
+            )
+            .await?
+        }
+    };
+
+    let priority_ops = |l2_sequencer: Option<String>,
+                        priority_ops: PriorityOpsArgs|
+     -> Result<PriorityOps, Box<dyn Error>> {
+        let needs_sequencer = priority_ops.priority_ops_source == PriorityOpsSource::Sequencer
+            || priority_ops.cross_check_sequencer;
+        let l2_sequencer = match (l2_sequencer, &args.server_url) {
+            (Some(l2_sequencer), _) => Some(l2_sequencer),
+            (None, Some(_)) if needs_sequencer => {
+                return Err("You set --server-url, so you must specify --l2-sequencer".into());
+            }
+            (None, Some(_)) => None,
+            (None, None) => Some("http://localhost:3050".to_string()),
+        };
+        Ok(PriorityOps {
+            source: priority_ops.priority_ops_source,
+            l2_sequencer,
+            cross_check_sequencer: priority_ops.cross_check_sequencer,
+            sequencer_batch_size: priority_ops.sequencer_batch_size,
+            sequencer_concurrency: priority_ops.sequencer_concurrency,
+            index_dir: args.index_dir.clone(),
+        })
+    };
 
     match args.command {
         Command::Show {} => {
-            let total_committed = contract.getTotalBatchesCommitted().call().await.unwrap();
-            let total_verified = contract.getTotalBatchesVerified().call().await.unwrap();
-            let total_executed = contract.getTotalBatchesExecuted().call().await.unwrap();
-            let semver = contract.getSemverProtocolVersion().call().await.unwrap();
-            println!("Using diamond Proxy: {}", args.address);
-            println!(
-                "Using Verifier: {}",
-                contract.getVerifier().call().await.unwrap()
-            );
-            println!("Total batches committed: {}", total_committed);
-            println!("Total batches verified: {}", total_verified);
-            println!("Total batches executed: {}", total_executed);
-            println!("Batches recovered from sequencer: {}", batches.len());
-
-            println!(
-                "Protocol version: {}.{}.{}",
-                semver._0, semver._1, semver._2
-            );
+            let semver = contract.getSemverProtocolVersion().call().await?;
+            Status {
+                diamond_proxy: address,
+                verifier: contract.getVerifier().call().await?,
+                total_batches_committed: contract
+                    .getTotalBatchesCommitted()
+                    .call()
+                    .await?
+                    .try_into()?,
+                total_batches_verified: contract
+                    .getTotalBatchesVerified()
+                    .call()
+                    .await?
+                    .try_into()?,
+                total_batches_executed: contract
+                    .getTotalBatchesExecuted()
+                    .call()
+                    .await?
+                    .try_into()?,
+                batches_in_index: stored.len(),
+                protocol_version: format!("{}.{}.{}", semver._0, semver._1, semver._2),
+            }
+            .print(output);
+            Ok(true)
+        }
+        Command::ExportBatches { out } => {
+            let l1_chain_id = provider.get_chain_id().await?;
+            BatchesFile::new(l1_chain_id, address, &batches, &stored).save(&out)?;
+            ExportedBatches {
+                path: out,
+                l1_chain_id,
+                diamond_proxy: address,
+                first_batch: stored.keys().min().copied(),
+                last_batch: stored.keys().max().copied(),
+                count: stored.len(),
+            }
+            .print(output);
+            Ok(true)
         }
         Command::PublicInput { range } => {
             let start = range.start;
             let end = range.end;
 
-            if start > end {
-                panic!("Start must be less than or equal to end");
-            }
+            check_batch_range(&stored, start, end)?;
 
+            let mut batches = vec![];
             for i in start..=end {
-                let batch = stored.get(&i).expect("Batch not found");
-                let prev_batch = stored.get(&(i - 1)).expect("Previous batch not found");
-                let public_input = get_batch_public_input(prev_batch, batch);
-                let snark_public_input = shift_b256_right(&public_input);
-                println!("FRI Public input for batch {}: {}", i, public_input);
-                println!("SNARK Public input for batch {}: {}", i, snark_public_input);
+                let public_input = get_batch_public_input(&stored[&(i - 1)], &stored[&i]);
+                batches.push(BatchPublicInput {
+                    batch_number: i,
+                    fri_public_input: public_input,
+                    snark_public_input: shift_b256_right(&public_input),
+                });
             }
-            println!(
-                "Snark public input for range {}-{}: {}",
+            PublicInputs {
                 start,
                 end,
-                snark_public_input_for_range(&stored, start, end)
-            );
+                batches,
+                snark_public_input: snark_public_input_for_range(&stored, start, end)?,
+            }
+            .print(output);
+            Ok(true)
         }
         Command::Prove {
             snark_path,
             range,
             snark_start,
+            proof_type,
+            vk_path,
+            skip_vk_check,
         } => {
-            prove_batches(
+            let result = prove_batches(
                 contract,
                 range.start,
                 range.end,
                 &stored,
+                proof_type,
                 snark_start,
                 snark_path,
+                vk_path,
+                skip_vk_check,
+                &args.fees,
                 dry_run,
             )
-            .await
+            .await?;
+            result.print(output);
+            Ok(!result.is_failed())
         }
         Command::FakeProve {
             public_input,
             range,
         } => {
-            fake_prove_batches(
+            check_batch_range(&stored, range.start, range.end)?;
+            let computed = snark_public_input_for_range(&stored, range.start, range.end)?;
+            if let Some(public_input) = public_input {
+                // Accepted with or without 0x (and without the leading zeros).
+                let public_input: B256 =
+                    U256::from_str_radix(public_input.trim_start_matches("0x"), 16)
+                        .map_err(|err| format!("Invalid --public-input {}: {}", public_input, err))?
+                        .into();
+                if public_input != computed {
+                    return Err(format!(
+                        "--public-input doesn't match the batches {}-{}:\n  given:    {}\n  computed: {}",
+                        range.start, range.end, public_input, computed
+                    )
+                    .into());
+                }
+            }
+            let public_input = computed;
+            let result = fake_prove_batches(
                 contract,
                 range.start,
                 range.end,
                 &stored,
                 public_input,
+                &args.fees,
                 dry_run,
             )
-            .await
+            .await?;
+            result.print(output);
+            Ok(!result.is_failed())
         }
         Command::Execute {
             range,
             l2_sequencer,
+            priority_ops: priority_ops_args,
         } => {
-            let l2_sequencer = l2_sequencer.unwrap_or_else(|| match args.server_url {
-                Some(_) => panic!("You set --server-url, so you must specify --l2-sequencer"),
-                None => "http://localhost:3050".to_string(),
-            });
-            execute_batches(
+            let result = execute_batches(
                 contract,
                 range.start,
                 range.end,
-                &l2_sequencer,
+                &priority_ops(l2_sequencer, priority_ops_args)?,
                 &stored,
+                &args.fees,
                 dry_run,
             )
+            .await?;
+            result.print(output);
+            Ok(!result.is_failed())
+        }
+        Command::PriorityOpProof {
+            tx_hash,
+            l2_sequencer,
+            priority_ops: priority_ops_args,
+        } => {
+            let proof = priority_op_proof(
+                &contract,
+                &tx_hash,
+                &priority_ops(l2_sequencer, priority_ops_args)?,
+                &stored,
+            )
+            .await?;
+            print_json(&proof);
+            Ok(true)
+        }
+        Command::InspectTx { .. } | Command::Revert { .. } => unreachable!(),
+        Command::Watch {
+            l2_sequencer,
+            priority_ops: priority_ops_args,
+            poll_interval,
+            max_backoff,
+        } => {
+            if dry_run {
+                return Err("please provide --private-key to run this command".into());
+            }
+            if args.batches_file.is_some() {
+                return Err(
+                    "watch always fetches the new batches - it can't use --batches-file".into(),
+                );
+            }
+            watch_batches(
+                contract,
+                &args.index_dir,
+                &priority_ops(l2_sequencer, priority_ops_args)?,
+                &args.fees,
+                output,
+                Duration::from_secs(poll_interval),
+                Duration::from_secs(max_backoff),
+            )
             .await;
+            Ok(true)
         }
-        Command::FakeProveAndExecute { l2_sequencer } => {
-            let l2_sequencer = l2_sequencer.unwrap_or_else(|| match args.server_url {
-                Some(_) => panic!("You set --server-url, so you must specify --l2-sequencer"),
-                None => "http://localhost:3050".to_string(),
-            });
+        Command::FakeProveAndExecute {
+            l2_sequencer,
+            priority_ops: priority_ops_args,
+        } => {
+            let priority_ops = priority_ops(l2_sequencer, priority_ops_args)?;
             if dry_run {
-                panic!("please provide --private-key to run this command");
+                return Err("please provide --private-key to run this command".into());
             }
 
             let total_committed = contract
                 .getTotalBatchesCommitted()
                 .call()
-                .await
-                .unwrap()
-                .try_into()
-                .unwrap();
+                .await?
+                .try_into()?;
             let total_verified: u64 = contract
                 .getTotalBatchesVerified()
                 .call()
-                .await
-                .unwrap()
-                .try_into()
-                .unwrap();
+                .await?
+                .try_into()?;
             let total_executed: u64 = contract
                 .getTotalBatchesExecuted()
                 .call()
-                .await
-                .unwrap()
-                .try_into()
-                .unwrap();
+                .await?
+                .try_into()?;
+
+            let mut result = ProveAndExecute {
+                prove: None,
+                execute: None,
+            };
 
             if total_committed != total_verified {
-                println!(
+                eprintln!(
                     "Fake proving from {} to {}",
                     total_verified + 1,
                     total_committed
                 );
                 let public_input =
-                    snark_public_input_for_range(&stored, total_verified + 1, total_committed);
-                fake_prove_batches(
+                    snark_public_input_for_range(&stored, total_verified + 1, total_committed)?;
+                let prove = fake_prove_batches(
                     contract.clone(),
                     total_verified + 1,
                     total_committed,
                     &stored,
-                    public_input.to_string(),
+                    public_input,
+                    &args.fees,
                     dry_run,
                 )
-                .await;
+                .await?;
+                result.prove = Some(prove);
             }
 
-            if total_executed != total_committed {
-                println!(
+            if total_executed != total_committed && !result.is_failed() {
+                eprintln!(
                     "Executing from {} to {}",
                     total_executed + 1,
                     total_committed
                 );
-                execute_batches(
+                let execute = execute_batches(
                     contract,
                     total_executed + 1,
                     total_committed,
-                    &l2_sequencer,
+                    &priority_ops,
                     &stored,
+                    &args.fees,
                     dry_run,
                 )
-                .await;
-                println!(
-                    "\x1b[32mAll batches (up to batch {}) proven and executed\x1b[0m",
-                    total_committed
-                );
-            } else {
-                println!("Nothing to execute, all batches are executed already");
+                .await?;
+                result.execute = Some(execute);
             }
+            result.print(output);
+            Ok(!result.is_failed())
         }
-    };
+    }
 }
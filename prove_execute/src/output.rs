@@ -0,0 +1,270 @@
+// Results of the commands - printed either as text, or as JSON (for scripts).
+// In JSON mode only the result goes to stdout (progress is always printed to stderr).
+
+use std::fmt;
+
+use alloy::{
+    primitives::{Address, B256},
+    rpc::types::TransactionReceipt,
+};
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TxStatus {
+    /// Only the call was done (no private key).
+    DryRun,
+    Success,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TxAction {
+    Prove,
+    Execute,
+    Revert,
+}
+
+impl fmt::Display for TxAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxAction::Prove => write!(f, "Prove"),
+            TxAction::Execute => write!(f, "Execute"),
+            TxAction::Revert => write!(f, "Revert"),
+        }
+    }
+}
+
+/// Result of a prove, execute or revert.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxResult {
+    pub action: TxAction,
+    pub start: u64,
+    pub end: u64,
+    pub status: TxStatus,
+    pub tx_hash: Option<B256>,
+    pub block_number: Option<u64>,
+    pub gas_used: Option<u64>,
+}
+
+impl TxResult {
+    pub fn dry_run(action: TxAction, start: u64, end: u64) -> Self {
+        Self {
+            action,
+            start,
+            end,
+            status: TxStatus::DryRun,
+            tx_hash: None,
+            block_number: None,
+            gas_used: None,
+        }
+    }
+
+    pub fn from_receipt(
+        action: TxAction,
+        start: u64,
+        end: u64,
+        receipt: &TransactionReceipt,
+    ) -> Self {
+        Self {
+            action,
+            start,
+            end,
+            status: if receipt.status() {
+                TxStatus::Success
+            } else {
+                TxStatus::Failed
+            },
+            tx_hash: Some(receipt.transaction_hash),
+            block_number: receipt.block_number,
+            gas_used: Some(receipt.gas_used),
+        }
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.status == TxStatus::Failed
+    }
+
+    pub fn print(&self, output: OutputFormat) {
+        match output {
+            OutputFormat::Text => self.print_text(),
+            OutputFormat::Json => print_json(self),
+        }
+    }
+
+    fn print_text(&self) {
+        match self.status {
+            TxStatus::DryRun => {
+                println!("\x1b[32m{} call was successful.\x1b[0m", self.action);
+                println!("\x1b[31mThis was just a dry-run\x1b[0m");
+            }
+            TxStatus::Success => println!(
+                "{} of batches {}-{} succeeded: {} (block {:?}, gas used {:?})",
+                self.action,
+                self.start,
+                self.end,
+                self.tx_hash.unwrap_or_default(),
+                self.block_number,
+                self.gas_used
+            ),
+            TxStatus::Failed => println!(
+                "\x1b[31m{} of batches {}-{} failed: {}\x1b[0m",
+                self.action,
+                self.start,
+                self.end,
+                self.tx_hash.unwrap_or_default()
+            ),
+        }
+    }
+}
+
+pub fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+/// Result of `show`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Status {
+    pub diamond_proxy: Address,
+    pub verifier: Address,
+    pub total_batches_committed: u64,
+    pub total_batches_verified: u64,
+    pub total_batches_executed: u64,
+    pub batches_in_index: usize,
+    pub protocol_version: String,
+}
+
+impl Status {
+    pub fn print(&self, output: OutputFormat) {
+        match output {
+            OutputFormat::Text => {
+                println!("Using diamond Proxy: {}", self.diamond_proxy);
+                println!("Using Verifier: {}", self.verifier);
+                println!("Total batches committed: {}", self.total_batches_committed);
+                println!("Total batches verified: {}", self.total_batches_verified);
+                println!("Total batches executed: {}", self.total_batches_executed);
+                println!("Batches in the index: {}", self.batches_in_index);
+                println!("Protocol version: {}", self.protocol_version);
+            }
+            OutputFormat::Json => print_json(self),
+        }
+    }
+}
+
+/// Result of `export-batches`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedBatches {
+    pub path: String,
+    pub l1_chain_id: u64,
+    pub diamond_proxy: Address,
+    pub first_batch: Option<u64>,
+    pub last_batch: Option<u64>,
+    pub count: usize,
+}
+
+impl ExportedBatches {
+    pub fn print(&self, output: OutputFormat) {
+        match output {
+            OutputFormat::Text => match (self.first_batch, self.last_batch) {
+                (Some(first), Some(last)) => println!(
+                    "Exported {} batches ({}-{}) to {}",
+                    self.count, first, last, self.path
+                ),
+                _ => println!("Exported no batches to {}", self.path),
+            },
+            OutputFormat::Json => print_json(self),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchPublicInput {
+    pub batch_number: u64,
+    pub fri_public_input: B256,
+    pub snark_public_input: B256,
+}
+
+/// Result of `public-input`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicInputs {
+    pub start: u64,
+    pub end: u64,
+    pub batches: Vec<BatchPublicInput>,
+    /// SNARK public input for the whole range.
+    pub snark_public_input: B256,
+}
+
+impl PublicInputs {
+    pub fn print(&self, output: OutputFormat) {
+        match output {
+            OutputFormat::Text => {
+                for batch in &self.batches {
+                    println!(
+                        "FRI Public input for batch {}: {}",
+                        batch.batch_number, batch.fri_public_input
+                    );
+                    println!(
+                        "SNARK Public input for batch {}: {}",
+                        batch.batch_number, batch.snark_public_input
+                    );
+                }
+                println!(
+                    "Snark public input for range {}-{}: {}",
+                    self.start, self.end, self.snark_public_input
+                );
+            }
+            OutputFormat::Json => print_json(self),
+        }
+    }
+}
+
+/// Result of `fake-prove-and-execute` (None if there was nothing to do).
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProveAndExecute {
+    pub prove: Option<TxResult>,
+    pub execute: Option<TxResult>,
+}
+
+impl ProveAndExecute {
+    pub fn is_failed(&self) -> bool {
+        self.prove
+            .iter()
+            .chain(&self.execute)
+            .any(TxResult::is_failed)
+    }
+
+    pub fn print(&self, output: OutputFormat) {
+        match output {
+            OutputFormat::Text => {
+                for result in self.prove.iter().chain(&self.execute) {
+                    result.print_text();
+                }
+                match &self.execute {
+                    Some(execute) if !execute.is_failed() => println!(
+                        "\x1b[32mAll batches (up to batch {}) proven and executed\x1b[0m",
+                        execute.end
+                    ),
+                    None if !self.is_failed() => {
+                        println!("Nothing to execute, all batches are executed already")
+                    }
+                    _ => {}
+                }
+            }
+            OutputFormat::Json => print_json(self),
+        }
+    }
+}
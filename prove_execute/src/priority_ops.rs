@@ -0,0 +1,555 @@
+// Priority operations (L1 -> L2 transactions) that were processed by each batch.
+// They can come either from the L2 sequencer, or from the `NewPriorityRequest` events on L1
+// (so that execute doesn't depend on the sequencer being up - or honest).
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fs,
+    ops::Range,
+    path::PathBuf,
+};
+
+use alloy::{
+    primitives::{Address, B256, keccak256},
+    providers::Provider,
+    rpc::types::Filter,
+    sol_types::SolEvent,
+};
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use common::{
+    BatchIndex,
+    IHyperchain::{self, IHyperchainInstance},
+    MiniMerkleTree, StoredBatchInfo,
+};
+
+use crate::sequencer::Sequencer;
+
+/// Bump whenever the format of the cache file changes - older files are then ignored.
+const CACHE_VERSION: u32 = 2;
+/// How many L2 blocks are fetched before the cache is saved.
+const BLOCKS_PER_SAVE: usize = 10_000;
+
+/// How far back (in L1 blocks) we look for the priority requests.
+const MAX_SCAN_BLOCKS: u64 = 1_000_000;
+const SCAN_CHUNK_SIZE: u64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PriorityOpsSource {
+    /// Priority transactions (type 0x2a) from the L2 blocks.
+    Sequencer,
+    /// `NewPriorityRequest` events emitted by the diamond proxy.
+    L1,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct PriorityOpsArgs {
+    /// Where to take the priority operations from.
+    #[arg(long, value_enum, default_value_t = PriorityOpsSource::Sequencer)]
+    pub priority_ops_source: PriorityOpsSource,
+    /// When taking them from L1 - also check that the sequencer has the same ones.
+    #[arg(long)]
+    pub cross_check_sequencer: bool,
+    /// How many calls are put into a single JSON-RPC batch request to the sequencer.
+    #[arg(long, default_value_t = 100)]
+    pub sequencer_batch_size: usize,
+    /// How many batch requests can be sent to the sequencer at the same time.
+    #[arg(long, default_value_t = 8)]
+    pub sequencer_concurrency: usize,
+}
+
+/// Everything needed to figure out the priority operations of the batches.
+pub struct PriorityOps {
+    pub source: PriorityOpsSource,
+    pub l2_sequencer: Option<String>,
+    pub cross_check_sequencer: bool,
+    pub sequencer_batch_size: usize,
+    pub sequencer_concurrency: usize,
+    /// Where the cache of the priority transactions (per L2 block) is kept.
+    pub index_dir: String,
+}
+
+impl PriorityOps {
+    /// Returns canonical hashes of the priority operations, per batch.
+    /// Contains all the batches up to `end` - as all of them are needed to build the priority tree.
+    pub async fn fetch<P: Provider>(
+        &self,
+        contract: &IHyperchainInstance<P>,
+        start: u64,
+        end: u64,
+        stored: &HashMap<u64, StoredBatchInfo>,
+        requests: &mut PriorityRequests,
+    ) -> Result<HashMap<u64, Vec<B256>>, Box<dyn Error>> {
+        match self.source {
+            PriorityOpsSource::Sequencer => self.fetch_from_sequencer(contract, end).await,
+            PriorityOpsSource::L1 => {
+                let l1_txs = priority_ops_from_l1(contract, start, end, stored, requests).await?;
+                if self.cross_check_sequencer {
+                    let sequencer_txs = self.fetch_from_sequencer(contract, end).await?;
+                    cross_check(&l1_txs, &sequencer_txs, start, end)?;
+                }
+                Ok(l1_txs)
+            }
+        }
+    }
+
+    async fn fetch_from_sequencer<P: Provider>(
+        &self,
+        contract: &IHyperchainInstance<P>,
+        end: u64,
+    ) -> Result<HashMap<u64, Vec<B256>>, Box<dyn Error>> {
+        let l2_sequencer = self
+            .l2_sequencer
+            .as_deref()
+            .ok_or("L2 sequencer is required for this priority ops source")?;
+        let sequencer = Sequencer::new(
+            l2_sequencer,
+            self.sequencer_batch_size,
+            self.sequencer_concurrency,
+        )?;
+        let l1_chain_id = contract.provider().get_chain_id().await?;
+        let mut cache = PriorityOpsCache::load(&self.index_dir, l1_chain_id, *contract.address());
+        cache.apply_reverts(&BatchIndex::load(
+            &self.index_dir,
+            l1_chain_id,
+            *contract.address(),
+        ));
+        priority_ops_from_sequencer(&sequencer, &mut cache, &self.index_dir, end).await
+    }
+}
+
+/// Priority transactions of the L2 blocks that were already fetched from the sequencer
+/// (kept next to the batch index, so that we don't refetch the whole chain on every run).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PriorityOpsCache {
+    pub version: u32,
+    pub l1_chain_id: u64,
+    pub diamond_proxy: Address,
+    /// Last L1 block of the batch index whose reverts were already applied to the cache.
+    pub synced_l1_block: Option<u64>,
+    /// L2 blocks (both ends inclusive) of each batch (only the ones reported by the sequencer).
+    pub batches: BTreeMap<u64, (u64, u64)>,
+    /// Hashes of the priority transactions in each L2 block.
+    pub blocks: BTreeMap<u64, Vec<B256>>,
+}
+
+impl PriorityOpsCache {
+    pub fn new(l1_chain_id: u64, diamond_proxy: Address) -> Self {
+        Self {
+            version: CACHE_VERSION,
+            l1_chain_id,
+            diamond_proxy,
+            synced_l1_block: None,
+            batches: Default::default(),
+            blocks: Default::default(),
+        }
+    }
+
+    fn path(index_dir: &str, l1_chain_id: u64, diamond_proxy: Address) -> PathBuf {
+        PathBuf::from(index_dir).join(format!(
+            "{}_{}_priority_ops.json",
+            l1_chain_id, diamond_proxy
+        ))
+    }
+
+    /// Loads the cache from disk - or returns an empty one if there is none (or it is outdated).
+    pub fn load(index_dir: &str, l1_chain_id: u64, diamond_proxy: Address) -> Self {
+        let path = Self::path(index_dir, l1_chain_id, diamond_proxy);
+        fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<PriorityOpsCache>(&content).ok())
+            .filter(|cache| cache.version == CACHE_VERSION)
+            .unwrap_or_else(|| Self::new(l1_chain_id, diamond_proxy))
+    }
+
+    pub fn save(&self, index_dir: &str) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(index_dir)?;
+        let path = Self::path(index_dir, self.l1_chain_id, self.diamond_proxy);
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Applies the reverts that the batch index found since the last time (including the ones that were
+    /// not sent by us) - as the reverted batches can be re-committed with different priority operations.
+    pub fn apply_reverts(&mut self, index: &BatchIndex) {
+        if let Some(synced) = self.synced_l1_block {
+            for (block, total_committed) in index.reverts.range(synced + 1..) {
+                eprintln!(
+                    "Batches reverted to {} at L1 block {} - dropping the cached priority operations above it",
+                    total_committed, block
+                );
+                self.revert_to(*total_committed);
+            }
+        }
+        if index.last_scanned_block.is_some() {
+            self.synced_l1_block = index.last_scanned_block;
+        }
+    }
+
+    /// Forgets the batches above `last_batch` (and their blocks) - as they can be re-sealed differently.
+    pub fn revert_to(&mut self, last_batch: u64) {
+        self.batches.split_off(&(last_batch + 1));
+        let last_block = self
+            .batches
+            .values()
+            .map(|(_, last_block)| *last_block)
+            .max()
+            .unwrap_or_default();
+        self.blocks.split_off(&(last_block + 1));
+    }
+}
+
+/// Priority transactions from the sequencer, aggregated per batch (for batches 1..=end).
+/// Only the blocks that are not in the cache yet are fetched - the cache is saved after every chunk,
+/// so that the progress is not lost if we're interrupted.
+pub async fn priority_ops_from_sequencer(
+    sequencer: &Sequencer,
+    cache: &mut PriorityOpsCache,
+    index_dir: &str,
+    end: u64,
+) -> Result<HashMap<u64, Vec<B256>>, Box<dyn Error>> {
+    // Actually start from batch 1.
+    let missing_batches: Vec<u64> = (1..=end)
+        .filter(|batch| !cache.batches.contains_key(batch))
+        .collect();
+    let mut batches = cache.batches.clone();
+    if !missing_batches.is_empty() {
+        let ranges = sequencer.batch_block_ranges(&missing_batches).await?;
+        for (batch, range) in missing_batches.into_iter().zip(ranges) {
+            match range {
+                Some(range) => {
+                    cache.batches.insert(batch, range);
+                    batches.insert(batch, range);
+                }
+                // The sequencer doesn't know the ranges - assume a single block per batch, but don't cache it.
+                None => {
+                    batches.insert(batch, (batch, batch));
+                }
+            }
+        }
+    }
+
+    let missing_blocks: Vec<u64> = (1..=end)
+        .flat_map(|batch| {
+            let (first_block, last_block) = batches[&batch];
+            first_block..=last_block
+        })
+        .filter(|block| !cache.blocks.contains_key(block))
+        .collect();
+    let mut fetched = 0;
+    for chunk in missing_blocks.chunks(BLOCKS_PER_SAVE) {
+        let txs = sequencer.priority_txs(chunk).await?;
+        cache.blocks.extend(chunk.iter().copied().zip(txs));
+        cache.save(index_dir)?;
+        fetched += chunk.len();
+        eprintln!(
+            "Fetched {} out of {} L2 blocks from the sequencer",
+            fetched,
+            missing_blocks.len()
+        );
+    }
+    cache.save(index_dir)?;
+
+    Ok((1..=end)
+        .map(|batch| {
+            let (first_block, last_block) = batches[&batch];
+            let txs = (first_block..=last_block)
+                .flat_map(|block| cache.blocks[&block].iter().copied())
+                .collect();
+            (batch, txs)
+        })
+        .collect())
+}
+
+/// Priority operations from the `NewPriorityRequest` events, split into batches using their `numberOfLayer1Txs`.
+/// Operations processed before `start` (by the already executed batches) are put under batch `start - 1`
+/// (the ones before the priority tree's start index only as zero placeholders).
+pub async fn priority_ops_from_l1<P: Provider>(
+    contract: &IHyperchainInstance<P>,
+    start: u64,
+    end: u64,
+    stored: &HashMap<u64, StoredBatchInfo>,
+    requests: &mut PriorityRequests,
+) -> Result<HashMap<u64, Vec<B256>>, Box<dyn Error>> {
+    let total_executed: u64 = contract
+        .getTotalBatchesExecuted()
+        .call()
+        .await?
+        .try_into()?;
+    // Number of priority operations processed by the executed batches.
+    let mut first_op: u64 = contract
+        .getFirstUnprocessedPriorityTx()
+        .call()
+        .await?
+        .try_into()?;
+    for batch in total_executed + 1..start {
+        first_op += number_of_layer1_txs(stored, batch)?;
+    }
+    let mut total_ops = first_op;
+    for batch in start..=end {
+        total_ops += number_of_layer1_txs(stored, batch)?;
+    }
+
+    // Operations before the priority tree's start index are not used (they were processed by the legacy
+    // priority queue) - and often are older than the scanned L1 blocks. So they are only zero placeholders,
+    // to keep the ids of the others.
+    let start_index: u64 = contract
+        .getPriorityTreeStartIndex()
+        .call()
+        .await?
+        .try_into()?;
+    let fetch_from = start_index.min(first_op);
+    let mut ops = vec![B256::ZERO; fetch_from as usize];
+    ops.extend(requests.get(contract, fetch_from..total_ops).await?);
+
+    let mut result = HashMap::new();
+    if first_op > 0 {
+        if start < 2 {
+            return Err("Batch 1 cannot follow any priority operations".into());
+        }
+        result.insert(start - 1, ops[..first_op as usize].to_vec());
+    }
+    let mut next_op = first_op as usize;
+    for batch in start..=end {
+        let count = number_of_layer1_txs(stored, batch)? as usize;
+        result.insert(batch, ops[next_op..next_op + count].to_vec());
+        next_op += count;
+    }
+    Ok(result)
+}
+
+fn number_of_layer1_txs(
+    stored: &HashMap<u64, StoredBatchInfo>,
+    batch: u64,
+) -> Result<u64, Box<dyn Error>> {
+    let batch_info = stored
+        .get(&batch)
+        .ok_or(format!("Batch {} not found", batch))?;
+    Ok(batch_info.numberOfLayer1Txs.try_into()?)
+}
+
+/// `NewPriorityRequest`s that were already fetched from L1 (by their id) - each scan goes back up to
+/// `MAX_SCAN_BLOCKS`, so within a single execute every request is fetched only once.
+#[derive(Debug, Default)]
+pub struct PriorityRequests {
+    requests: BTreeMap<u64, B256>,
+}
+
+impl PriorityRequests {
+    /// Returns the canonical hashes of the priority requests with the given ids (ordered by their id),
+    /// fetching the ones that we don't have yet.
+    pub async fn get<P: Provider>(
+        &mut self,
+        contract: &IHyperchainInstance<P>,
+        ids: Range<u64>,
+    ) -> Result<Vec<B256>, Box<dyn Error>> {
+        let is_missing = |id: &u64| !self.requests.contains_key(id);
+        if let (Some(first), Some(last)) = (
+            ids.clone().find(is_missing),
+            ids.clone().rev().find(is_missing),
+        ) {
+            let fetched = fetch_priority_requests(contract, first..last + 1).await?;
+            self.requests.extend((first..).zip(fetched));
+        }
+        Ok(ids.map(|id| self.requests[&id]).collect())
+    }
+}
+
+/// Returns the canonical hashes of the priority requests with the given ids (ordered by their id).
+/// Scans L1 backwards, until all of them are found.
+async fn fetch_priority_requests<P: Provider>(
+    contract: &IHyperchainInstance<P>,
+    ids: Range<u64>,
+) -> Result<Vec<B256>, Box<dyn Error>> {
+    let count = ids.end - ids.start;
+    let provider = contract.provider();
+    let latest_block = provider.get_block_number().await?;
+    let first_block = latest_block.saturating_sub(MAX_SCAN_BLOCKS);
+
+    let mut requests = BTreeMap::new();
+    let mut to_block = latest_block;
+    loop {
+        let from_block = to_block
+            .saturating_sub(SCAN_CHUNK_SIZE - 1)
+            .max(first_block);
+        let filter = Filter::new()
+            .from_block(from_block)
+            .to_block(to_block)
+            .address(*contract.address())
+            .event_signature(IHyperchain::NewPriorityRequest::SIGNATURE_HASH);
+        for log in provider.get_logs(&filter).await? {
+            let event = log
+                .log_decode::<IHyperchain::NewPriorityRequest>()?
+                .inner
+                .data;
+            let tx_id: u64 = event.txId.try_into()?;
+            if ids.contains(&tx_id) {
+                requests.insert(tx_id, event.txHash);
+            }
+        }
+        // Ids are sequential - so once we have them all, we can stop.
+        if requests.len() as u64 == count || from_block == first_block {
+            break;
+        }
+        to_block = from_block - 1;
+    }
+
+    if requests.len() as u64 != count {
+        let missing = ids.clone().find(|id| !requests.contains_key(id)).unwrap();
+        return Err(format!(
+            "Priority request {} not found in the last {} L1 blocks (found {} out of {})",
+            missing,
+            MAX_SCAN_BLOCKS,
+            requests.len(),
+            count
+        )
+        .into());
+    }
+    Ok(requests.into_values().collect())
+}
+
+/// Checks that the sequencer has the same priority operations in the batches being executed.
+pub fn cross_check(
+    l1_txs: &HashMap<u64, Vec<B256>>,
+    sequencer_txs: &HashMap<u64, Vec<B256>>,
+    start: u64,
+    end: u64,
+) -> Result<(), Box<dyn Error>> {
+    for batch in start..=end {
+        let from_l1 = l1_txs.get(&batch).map(Vec::as_slice).unwrap_or_default();
+        let from_sequencer = sequencer_txs
+            .get(&batch)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if from_l1 != from_sequencer {
+            return Err(format!(
+                "Priority operations of batch {} differ: L1 has {:?}, sequencer has {:?}",
+                batch, from_l1, from_sequencer
+            )
+            .into());
+        }
+    }
+    eprintln!(
+        "Priority operations of batches {}-{} match the sequencer",
+        start, end
+    );
+    Ok(())
+}
+
+/// Checks the priority operations against L1 before executing:
+/// * each of the batches being executed must have the operations it committed to (count & rolling hash),
+/// * the priority tree built from them (and the operations requested later) must have the same root as the one on L1.
+///
+/// Operations before the tree's `start_index` were processed by the legacy priority queue, and are not part of it.
+pub async fn validate_priority_tree<P: Provider>(
+    contract: &IHyperchainInstance<P>,
+    l1_txs: &HashMap<u64, Vec<B256>>,
+    start: u64,
+    end: u64,
+    stored: &HashMap<u64, StoredBatchInfo>,
+    start_index: u64,
+    requests: &mut PriorityRequests,
+) -> Result<(), Box<dyn Error>> {
+    // All the priority operations that we know about - with the batch that processed them (ordered by their id).
+    let mut batches: Vec<u64> = l1_txs.keys().copied().collect();
+    batches.sort_unstable();
+    let ops: Vec<(u64, B256)> = batches
+        .iter()
+        .flat_map(|batch| l1_txs[batch].iter().map(move |tx| (*batch, *tx)))
+        .collect();
+
+    for batch in start..=end {
+        let batch_info = stored
+            .get(&batch)
+            .ok_or(format!("Batch {} not found", batch))?;
+        let batch_ops = l1_txs.get(&batch).map(Vec::as_slice).unwrap_or_default();
+        let first_id = ops
+            .iter()
+            .position(|(b, _)| *b == batch)
+            .unwrap_or(ops.len());
+        let rolling_hash = batch_ops
+            .iter()
+            .fold(keccak256([]), |hash, tx| keccak256([hash, *tx].concat()));
+        if batch_ops.len() as u64 != number_of_layer1_txs(stored, batch)?
+            || rolling_hash != batch_info.priorityOperationsHash
+        {
+            return Err(format!(
+                "Priority operations of batch {} (starting at priority op {}) don't match its commitment: \
+                 it has {} operations with rolling hash {}, but we have {} with hash {}",
+                batch,
+                first_id,
+                batch_info.numberOfLayer1Txs,
+                batch_info.priorityOperationsHash,
+                batch_ops.len(),
+                rolling_hash
+            )
+            .into());
+        }
+    }
+
+    let total: u64 = contract.getTotalPriorityTxs().call().await?.try_into()?;
+    let known = ops.len() as u64;
+    if known > total {
+        return Err(format!(
+            "We have {} priority operations, but only {} were requested on L1",
+            known, total
+        )
+        .into());
+    }
+    if total <= start_index {
+        return Err(format!(
+            "Priority tree is empty (start index {}, {} operations requested)",
+            start_index, total
+        )
+        .into());
+    }
+    // The root on L1 includes the operations that were requested after the ones we have.
+    let later_ops = requests.get(contract, known..total).await?;
+    let mut tree = MiniMerkleTree::new();
+    for tx in ops
+        .iter()
+        .map(|(_, tx)| tx)
+        .chain(&later_ops)
+        .skip(start_index as usize)
+    {
+        tree.push_hash(*tx);
+    }
+    let l1_root = contract.getPriorityTreeRoot().call().await?;
+    if tree.root() == l1_root {
+        eprintln!(
+            "Priority tree root matches L1: {} ({} leaves, start index {})",
+            l1_root,
+            total - start_index,
+            start_index
+        );
+        return Ok(());
+    }
+
+    // Find the first operation that differs from the one requested on L1.
+    let requested = requests.get(contract, start_index..known).await?;
+    let diverging = ops
+        .iter()
+        .enumerate()
+        .skip(start_index as usize)
+        .zip(&requested)
+        .find(|((_, (_, ours)), theirs)| ours != *theirs);
+    match diverging {
+        Some(((id, (batch, ours)), theirs)) => Err(format!(
+            "Priority tree diverges at leaf {} (priority op {}, batch {}): we have {}, L1 has {}",
+            id as u64 - start_index,
+            id,
+            batch,
+            ours,
+            theirs
+        )
+        .into()),
+        None => Err(format!(
+            "Priority tree root {} doesn't match L1 root {}, even though all the leaves match",
+            tree.root(),
+            l1_root
+        )
+        .into()),
+    }
+}
@@ -0,0 +1,98 @@
+// Merkle proof that a priority operation (L1 -> L2 transaction) is in the priority tree.
+
+use std::{collections::HashMap, error::Error};
+
+use alloy::{hex::FromHex, primitives::B256, providers::Provider};
+use serde::Serialize;
+
+use common::{
+    IHyperchain::IHyperchainInstance,
+    StoredBatchInfo,
+    merkle::{calculate_root, verify_path},
+};
+
+use crate::{
+    l1_merkle::MerkleInfoForExecute,
+    priority_ops::{PriorityOps, PriorityRequests},
+};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriorityOpProof {
+    /// Canonical hash of the transaction (the leaf).
+    pub tx_hash: B256,
+    /// Batch that processed it.
+    pub batch_number: u64,
+    /// Id of the priority operation (`txId` of the `NewPriorityRequest` event).
+    pub priority_op_id: u64,
+    /// Index of the leaf - ids before the tree's start index were processed by the legacy priority queue.
+    pub leaf_index: u64,
+    /// Root of the tree with all the operations processed by the committed batches
+    /// (a historical root of the priority tree on L1).
+    pub root: B256,
+    /// Merkle path from the leaf up to the root.
+    pub path: Vec<B256>,
+}
+
+/// Builds the priority tree from the operations of all the committed batches, and returns the proof
+/// for the one with a given canonical tx hash.
+pub async fn priority_op_proof<P: Provider>(
+    contract: &IHyperchainInstance<P>,
+    tx_hash: &str,
+    priority_ops: &PriorityOps,
+    stored: &HashMap<u64, StoredBatchInfo>,
+) -> Result<PriorityOpProof, Box<dyn Error>> {
+    let tx_hash = B256::from_hex(tx_hash)?;
+    let total_committed: u64 = contract
+        .getTotalBatchesCommitted()
+        .call()
+        .await?
+        .try_into()?;
+    let total_executed: u64 = contract
+        .getTotalBatchesExecuted()
+        .call()
+        .await?
+        .try_into()?;
+    let start_index: u64 = contract
+        .getPriorityTreeStartIndex()
+        .call()
+        .await?
+        .try_into()?;
+
+    let l1_tx_map = priority_ops
+        .fetch(
+            contract,
+            total_executed + 1,
+            total_committed,
+            stored,
+            &mut PriorityRequests::default(),
+        )
+        .await?;
+    let merkle_info = MerkleInfoForExecute::init(&l1_tx_map, start_index);
+
+    let (batch_number, leaf_index) = merkle_info.find_l1_tx(&tx_hash).ok_or(format!(
+        "Priority operation {} is not in the priority tree of batches up to {} \
+         (it is not committed yet, or was processed by the legacy priority queue)",
+        tx_hash, total_committed
+    ))?;
+    let (root, path) = merkle_info.get_merkle_path_for_leaf(leaf_index);
+    if !verify_path(root, &path, leaf_index as usize, tx_hash) {
+        return Err(format!(
+            "Merkle path of priority operation {} ({}) doesn't verify: it gives root {}, but the tree has {}",
+            leaf_index + start_index,
+            tx_hash,
+            calculate_root(&path, leaf_index as usize, tx_hash),
+            root
+        )
+        .into());
+    }
+
+    Ok(PriorityOpProof {
+        tx_hash,
+        batch_number,
+        priority_op_id: leaf_index + start_index,
+        leaf_index,
+        root,
+        path,
+    })
+}
@@ -1,29 +1,85 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, error::Error};
 
-use alloy::{primitives::U256, providers::Provider, sol_types::SolCall};
+use alloy::{
+    primitives::{B256, U256},
+    providers::{Provider, WalletProvider},
+    sol_types::SolCall,
+};
+use clap::ValueEnum;
 
-use crate::{
+use common::{
     IHyperchain::{self, IHyperchainInstance},
-    StoredBatchInfo, snark, snark_public_input_for_range,
+    StoredBatchInfo, check_batch_range, check_verification_key_hash, describe_call_error,
+    era_snark_public_input_for_batch, snark_public_input_for_range,
+};
+
+use crate::{
+    fees::FeePolicy,
+    output::{TxAction, TxResult},
+    snark,
 };
 
-const OHBENDER_PROOF_TYPE: i32 = 2;
 const FAKE_PROOF_TYPE: i32 = 3;
 const FAKE_PROOF_MAGIC_VALUE: i32 = 13;
 
-pub async fn fake_prove_batches<P: Provider + Clone>(
+/// Type of the SNARK proof - picks the verifier in DualVerifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProofType {
+    /// FFLONK wrapper proof of a single batch (the standard Era prover pipeline).
+    Fflonk,
+    /// PLONK wrapper proof of a single batch (the standard Era prover pipeline).
+    Plonk,
+    /// PLONK wrapper proof of a range of batches (with the previous hash).
+    Ohbender,
+}
+
+impl ProofType {
+    /// Type in the first element of the proof array.
+    pub fn verifier_type(self) -> u64 {
+        match self {
+            ProofType::Fflonk => 0,
+            ProofType::Plonk => 1,
+            ProofType::Ohbender => 2,
+        }
+    }
+
+    /// Type to pass to `DualVerifier.verificationKeyHash` - it only knows FFLONK and PLONK,
+    /// and ohbender proofs are checked by the PLONK verifier.
+    pub fn vk_verifier_type(self) -> u64 {
+        match self {
+            ProofType::Fflonk => 0,
+            ProofType::Plonk | ProofType::Ohbender => 1,
+        }
+    }
+
+    /// Public input that the proof must have for a given range of batches.
+    pub fn public_input(
+        self,
+        stored: &HashMap<u64, StoredBatchInfo>,
+        start: u64,
+        end: u64,
+    ) -> Result<B256, Box<dyn Error>> {
+        match self {
+            ProofType::Fflonk | ProofType::Plonk => {
+                if start != end {
+                    return Err(format!("{:?} proofs can prove only a single batch", self).into());
+                }
+                era_snark_public_input_for_batch(stored, start)
+            }
+            ProofType::Ohbender => snark_public_input_for_range(stored, start, end),
+        }
+    }
+}
+
+pub async fn fake_prove_batches<P: Provider + WalletProvider + Clone>(
     contract: IHyperchainInstance<P>,
     start: u64,
     end: u64,
     stored: &HashMap<u64, StoredBatchInfo>,
-    public_input: String,
+    public_input: B256,
+    fees: &FeePolicy,
     dry_run: bool,
-) {
-    let public_input = if public_input.starts_with("0x") {
-        public_input.trim_start_matches("0x").to_string()
-    } else {
-        public_input
-    };
+) -> Result<TxResult, Box<dyn Error>> {
     let proof: Vec<U256> = vec![
         // Fake proof type
         U256::from(FAKE_PROOF_TYPE),
@@ -32,76 +88,133 @@ pub async fn fake_prove_batches<P: Provider + Clone>(
         // Fake proof magic value (just for sanity)
         U256::from(FAKE_PROOF_MAGIC_VALUE),
         // Public input (fake proof **will** verify this against batch data stored in the contract)
-        U256::from_str_radix(&public_input, 16).unwrap(),
+        U256::from_be_slice(public_input.as_slice()),
     ];
 
-    prove_batches_internal(proof, contract, start, end, stored, dry_run).await;
+    prove_batches_internal(proof, contract, start, end, stored, fees, dry_run).await
 }
 
-pub async fn prove_batches<P: Provider + Clone>(
+#[allow(clippy::too_many_arguments)]
+pub async fn prove_batches<P: Provider + WalletProvider + Clone>(
     contract: IHyperchainInstance<P>,
     start: u64,
     end: u64,
     stored: &HashMap<u64, StoredBatchInfo>,
+    proof_type: ProofType,
     snark_start: Option<u64>,
     snark_path: String,
+    vk_path: Option<String>,
+    skip_vk_check: bool,
+    fees: &FeePolicy,
     dry_run: bool,
-) {
-    let data = snark::load_snark_from_file(&snark_path).unwrap();
+) -> Result<TxResult, Box<dyn Error>> {
+    check_batch_range(stored, start, end)?;
+    if let Some(snark_start) = snark_start {
+        if proof_type != ProofType::Ohbender {
+            // FFLONK and PLONK verifiers take the public input of a single batch.
+            return Err(format!("{:?} proofs can prove only a single batch", proof_type).into());
+        }
+        if snark_start > start {
+            return Err(format!(
+                "Snark start ({}) must be less than or equal to start ({})",
+                snark_start, start
+            )
+            .into());
+        }
+    }
+    // The snark covers all the batches from `snark_start`.
+    let expected_public_input =
+        proof_type.public_input(stored, snark_start.unwrap_or(start), end)?;
+
+    // The proof must be made with the key of the verifier on L1 (unless the check is explicitly skipped).
+    match &vk_path {
+        Some(vk_path) => {
+            let vk_hash = match proof_type {
+                ProofType::Fflonk => snark::fflonk_vk_hash(vk_path)?,
+                ProofType::Plonk | ProofType::Ohbender => snark::snark_vk_hash(vk_path)?,
+            };
+            check_verification_key_hash(
+                contract.provider(),
+                *contract.address(),
+                proof_type.vk_verifier_type(),
+                vk_hash,
+            )
+            .await?
+        }
+        None if skip_vk_check => eprintln!(
+            "Warning: --skip-vk-check - the proof is submitted without checking its verification key"
+        ),
+        None => {
+            return Err(format!(
+                "{:?} proofs need --vk-path (or --skip-vk-check to submit without checking the key)",
+                proof_type
+            )
+            .into());
+        }
+    }
+
+    let data = match proof_type {
+        ProofType::Fflonk => {
+            // There is no FFLONK verifier here - only the public input of the proof is checked.
+            snark::verify_fflonk_snark(&snark_path, expected_public_input)?;
+            snark::load_fflonk_snark_from_file(&snark_path)?
+        }
+        ProofType::Plonk | ProofType::Ohbender => {
+            snark::verify_snark(&snark_path, vk_path.as_deref(), expected_public_input)?;
+            snark::load_snark_from_file(&snark_path)?
+        }
+    };
     let mut proof: Vec<U256> = data
         .iter()
         .map(|x| U256::from_str_radix(x, 10).unwrap())
         .collect();
 
-    // ohbender type
-    proof.insert(0, U256::from(OHBENDER_PROOF_TYPE));
-
-    // FRI from batch 1.
-
-    let prev_hash = match snark_start {
-        Some(snark_start) => {
-            assert!(
-                snark_start <= start,
-                "Snark start must be less than or equal to start"
-            );
-            // If snark start is provided, we use it to get the previous batch.
-            if snark_start < start {
-                // compute keccak256 from snark_start to start-1 inclusive.
-                // TODO: check if BE or LE.
-                let public_input = snark_public_input_for_range(stored, snark_start, start - 1);
-                U256::from_be_slice(public_input.as_slice())
-            } else {
+    proof.insert(0, U256::from(proof_type.verifier_type()));
+
+    if proof_type == ProofType::Ohbender {
+        // FRI from batch 1.
+
+        let prev_hash = match snark_start {
+            Some(snark_start) => {
+                // If snark start is provided, we use it to get the previous batch.
+                if snark_start < start {
+                    // compute keccak256 from snark_start to start-1 inclusive.
+                    // TODO: check if BE or LE.
+                    let public_input =
+                        snark_public_input_for_range(stored, snark_start, start - 1)?;
+                    U256::from_be_slice(public_input.as_slice())
+                } else {
+                    U256::from(0)
+                }
+            }
+            None => {
+                // If none - then snark range is matching the range perfectly.
                 U256::from(0)
             }
-        }
-        None => {
-            // If none - then snark range is matching the range perfectly.
-            U256::from(0)
-        }
-    };
-    proof.insert(1, prev_hash);
+        };
+        proof.insert(1, prev_hash);
+    }
 
-    prove_batches_internal(proof, contract, start, end, stored, dry_run).await
+    prove_batches_internal(proof, contract, start, end, stored, fees, dry_run).await
 }
 
 pub async fn prove_batches_internal(
     proof: Vec<U256>,
-    contract: IHyperchainInstance<impl Provider>,
+    contract: IHyperchainInstance<impl Provider + WalletProvider>,
     start: u64,
     end: u64,
     stored: &HashMap<u64, StoredBatchInfo>,
+    fees: &FeePolicy,
     dry_run: bool,
-) {
-    let prev_batch = start - 1;
+) -> Result<TxResult, Box<dyn Error>> {
+    check_batch_range(stored, start, end)?;
 
     let mut proof_data = vec![0u8];
 
-    let new_batches = (start..=end)
-        .map(|x| stored.get(&x).unwrap().clone())
-        .collect();
+    let new_batches = (start..=end).map(|x| stored[&x].clone()).collect();
 
     let proof_payload = IHyperchain::proofPayloadCall {
-        old: stored.get(&prev_batch).unwrap().clone(),
+        old: stored[&(start - 1)].clone(),
         newInfo: new_batches,
         proof,
     };
@@ -117,9 +230,9 @@ pub async fn prove_batches_internal(
         )
         .call()
         .await
-        .unwrap();
+        .map_err(|err| format!("Prove call failed: {}", describe_call_error(&err)))?;
 
-    if dry_run == false {
+    if !dry_run {
         let tx = contract
             .proveBatchesSharedBridge(
                 0.try_into().unwrap(),
@@ -127,20 +240,16 @@ pub async fn prove_batches_internal(
                 end.try_into().unwrap(),
                 proof_data.into(),
             )
-            .max_priority_fee_per_gas(2_000_000_002)
-            .max_fee_per_gas(2_000_000_002)
-            .send()
-            .await
-            .unwrap();
-        println!("Transaction sent: {}", tx.tx_hash());
-        let receipt = tx.get_receipt().await.unwrap();
-        if receipt.status() {
-            println!("Transaction succeeded: {:?}", receipt);
-        } else {
-            println!("Transaction failed: {:?}", receipt);
-        }
+            .into_transaction_request();
+        let receipt = fees.send_with_replacement(contract.provider(), tx).await?;
+        eprintln!("Transaction receipt: {:?}", receipt);
+        Ok(TxResult::from_receipt(
+            TxAction::Prove,
+            start,
+            end,
+            &receipt,
+        ))
     } else {
-        println!("\x1b[32mProve call was successful.\x1b[0m");
-        println!("\x1b[31mThis was just a dry-run\x1b[0m");
+        Ok(TxResult::dry_run(TxAction::Prove, start, end))
     }
 }
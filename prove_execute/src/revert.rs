@@ -0,0 +1,82 @@
+// Reverting the committed (but not executed) batches - useful when a bad commit lands on a dev chain.
+
+use std::error::Error;
+
+use alloy::providers::{Provider, WalletProvider};
+
+use common::{BatchIndex, IHyperchain::IHyperchainInstance, describe_call_error};
+
+use crate::{
+    fees::FeePolicy,
+    output::{TxAction, TxResult},
+    priority_ops::PriorityOpsCache,
+};
+
+/// Reverts all the batches above `to` (so `to` becomes the last committed batch).
+pub async fn revert_batches<P: Provider + WalletProvider>(
+    contract: IHyperchainInstance<P>,
+    to: u64,
+    index_dir: &str,
+    fees: &FeePolicy,
+    dry_run: bool,
+) -> Result<TxResult, Box<dyn Error>> {
+    let total_committed: u64 = contract
+        .getTotalBatchesCommitted()
+        .call()
+        .await?
+        .try_into()?;
+    let total_executed: u64 = contract
+        .getTotalBatchesExecuted()
+        .call()
+        .await?
+        .try_into()?;
+
+    if to < total_executed {
+        return Err(format!(
+            "Cannot revert to batch {} - batches up to {} are already executed",
+            to, total_executed
+        )
+        .into());
+    }
+    if to >= total_committed {
+        return Err(format!(
+            "Nothing to revert - batch {} is not below the last committed batch {}",
+            to, total_committed
+        )
+        .into());
+    }
+    eprintln!("Reverting batches {} to {}", to + 1, total_committed);
+
+    contract
+        .revertBatchesSharedBridge(0.try_into().unwrap(), to.try_into().unwrap())
+        .call()
+        .await
+        .map_err(|err| format!("Revert call failed: {}", describe_call_error(&err)))?;
+
+    if dry_run {
+        return Ok(TxResult::dry_run(TxAction::Revert, to + 1, total_committed));
+    }
+
+    let tx = contract
+        .revertBatchesSharedBridge(0.try_into().unwrap(), to.try_into().unwrap())
+        .into_transaction_request();
+    let receipt = fees.send_with_replacement(contract.provider(), tx).await?;
+    eprintln!("Transaction receipt: {:?}", receipt);
+
+    if receipt.status() {
+        // The next sync would also pick up the BlocksRevert event - but don't keep the reverted batches around until then.
+        let l1_chain_id = contract.provider().get_chain_id().await?;
+        let mut index = BatchIndex::load(index_dir, l1_chain_id, *contract.address());
+        index.revert_to(to);
+        index.save(index_dir)?;
+        let mut cache = PriorityOpsCache::load(index_dir, l1_chain_id, *contract.address());
+        cache.revert_to(to);
+        cache.save(index_dir)?;
+    }
+    Ok(TxResult::from_receipt(
+        TxAction::Revert,
+        to + 1,
+        total_committed,
+        &receipt,
+    ))
+}
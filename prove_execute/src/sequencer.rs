@@ -0,0 +1,158 @@
+// JSON-RPC client for the L2 sequencer - sends the requests in batches, a few batches at a time.
+
+use std::error::Error;
+
+use alloy::{
+    hex::FromHex,
+    primitives::B256,
+    rpc::client::RpcClient,
+    transports::{TransportError, TransportResult},
+};
+use futures::{StreamExt, TryStreamExt, future, stream};
+use serde_json::Value;
+
+/// JSON-RPC error code of the methods that the node doesn't support.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+pub struct Sequencer {
+    client: RpcClient,
+    /// How many calls are put into a single JSON-RPC batch request.
+    batch_size: usize,
+    /// How many batch requests can be in flight at the same time.
+    concurrency: usize,
+}
+
+impl Sequencer {
+    pub fn new(url: &str, batch_size: usize, concurrency: usize) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::with_client(
+            RpcClient::new_http(url.parse()?),
+            batch_size,
+            concurrency,
+        ))
+    }
+
+    /// Same, but with a given client (e.g. a mocked one).
+    pub fn with_client(client: RpcClient, batch_size: usize, concurrency: usize) -> Self {
+        Self {
+            client,
+            batch_size: batch_size.max(1),
+            concurrency: concurrency.max(1),
+        }
+    }
+
+    /// Calls `method` once for each of the params - returns the results (or the errors of the single calls)
+    /// in the same order.
+    async fn call_many(
+        &self,
+        method: &'static str,
+        params: Vec<Value>,
+    ) -> Result<Vec<TransportResult<Value>>, Box<dyn Error>> {
+        let chunks: Vec<Vec<Value>> = params
+            .chunks(self.batch_size)
+            .map(|chunk| chunk.to_vec())
+            .collect();
+        let responses: Vec<Vec<TransportResult<Value>>> = stream::iter(chunks)
+            .map(|chunk| self.call_batch(method, chunk))
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+        Ok(responses.into_iter().flatten().collect())
+    }
+
+    async fn call_batch(
+        &self,
+        method: &'static str,
+        params: Vec<Value>,
+    ) -> Result<Vec<TransportResult<Value>>, Box<dyn Error>> {
+        let mut batch = self.client.new_batch();
+        let waiters = params
+            .iter()
+            .map(|params| batch.add_call::<_, Value>(method, params))
+            .collect::<Result<Vec<_>, _>>()?;
+        batch.send().await?;
+        // Responses to a batch request can come in any order - the client matches them by their id.
+        Ok(future::join_all(waiters).await)
+    }
+
+    /// L2 blocks (both ends inclusive) of each of the batches.
+    /// None if the sequencer doesn't support `zks_getL1BatchBlockRange` (method not found) - any other error
+    /// is returned.
+    pub async fn batch_block_ranges(
+        &self,
+        batches: &[u64],
+    ) -> Result<Vec<Option<(u64, u64)>>, Box<dyn Error>> {
+        let params = batches
+            .iter()
+            .map(|batch| serde_json::json!([batch]))
+            .collect();
+        let responses = self.call_many("zks_getL1BatchBlockRange", params).await?;
+
+        batches
+            .iter()
+            .zip(responses)
+            .map(|(&batch, response)| {
+                let result = match response {
+                    Ok(result) => result,
+                    Err(err) if is_method_not_found(&err) => return Ok(None),
+                    Err(err) => {
+                        return Err(format!(
+                            "Failed to get the L2 blocks of batch {} from the sequencer: {}",
+                            batch, err
+                        )
+                        .into());
+                    }
+                };
+                let range = result
+                    .as_array()
+                    .filter(|range| range.len() == 2)
+                    .ok_or_else(|| {
+                        format!("Batch {} not found on the sequencer: {}", batch, result)
+                    })?;
+                Ok(Some((
+                    parse_quantity(&range[0])?,
+                    parse_quantity(&range[1])?,
+                )))
+            })
+            .collect()
+    }
+
+    /// Hashes of the priority transactions (type 0x2a) in each of the blocks.
+    pub async fn priority_txs(&self, blocks: &[u64]) -> Result<Vec<Vec<B256>>, Box<dyn Error>> {
+        let params = blocks
+            .iter()
+            .map(|block| serde_json::json!([format!("0x{:x}", block), true]))
+            .collect();
+        let responses = self.call_many("eth_getBlockByNumber", params).await?;
+
+        blocks
+            .iter()
+            .zip(responses)
+            .map(|(block, response)| {
+                let result = response.map_err(|err| {
+                    format!("Failed to get block {} from the sequencer: {}", block, err)
+                })?;
+                let transactions = result["transactions"]
+                    .as_array()
+                    .ok_or_else(|| format!("Block {} not found: {}", block, result))?;
+                transactions
+                    .iter()
+                    .filter(|tx| tx["type"].as_str() == Some("0x2a"))
+                    .map(|tx| {
+                        let hash = tx["hash"].as_str().ok_or("Transaction without hash")?;
+                        Ok(B256::from_hex(hash)?)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn is_method_not_found(err: &TransportError) -> bool {
+    err.as_error_resp()
+        .is_some_and(|err| err.code == METHOD_NOT_FOUND)
+}
+
+fn parse_quantity(value: &Value) -> Result<u64, Box<dyn Error>> {
+    let value = value.as_str().ok_or("Quantity is not a string")?;
+    Ok(u64::from_str_radix(value.trim_start_matches("0x"), 16)?)
+}
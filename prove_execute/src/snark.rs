@@ -1,13 +1,98 @@
+use alloy::primitives::B256;
 use base64::{self, Engine};
-use bellman::{bn256::Bn256, plonk::better_better_cs::proof::Proof as PlonkProof};
-use circuit_definitions::circuit_definitions::aux_layer::ZkSyncSnarkWrapperCircuit;
+use bellman::{
+    bn256::{Bn256, Fr},
+    pairing::ff::{PrimeField, PrimeFieldRepr},
+    plonk::{
+        better_better_cs::{proof::Proof as PlonkProof, setup::VerificationKey, verifier::verify},
+        commitments::transcript::keccak_transcript::RollingKeccakTranscript,
+    },
+};
+use circuit_definitions::circuit_definitions::aux_layer::{
+    FflonkSnarkVerifierCircuitProof, FflonkSnarkVerifierCircuitVK, ZkSyncSnarkWrapperCircuit,
+};
 
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 use std::error::Error;
 use std::fs;
 
 /// Reads snark from a given file and returns it as serialized proof.
 pub fn load_snark_from_file(path: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let proof: PlonkProof<Bn256, ZkSyncSnarkWrapperCircuit> = load_proof(path)?;
+    let (_, serialized_proof) = crypto_codegen::serialize_proof(&proof);
+
+    Ok(serialized_proof.iter().map(|x| x.to_string()).collect())
+}
+
+/// Same as `load_snark_from_file`, but for the FFLONK wrapper proofs.
+pub fn load_fflonk_snark_from_file(path: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let proof: FflonkSnarkVerifierCircuitProof = load_proof(path)?;
+    let (_, serialized_proof) = crypto_codegen::serialize_fflonk_proof(&proof);
+
+    Ok(serialized_proof.iter().map(|x| x.to_string()).collect())
+}
+
+/// Checks that the public input of the FFLONK snark is the expected one (there is no local verifier for these).
+pub fn verify_fflonk_snark(path: &str, expected_public_input: B256) -> Result<(), Box<dyn Error>> {
+    let proof: FflonkSnarkVerifierCircuitProof = load_proof(path)?;
+    check_public_input(&proof.inputs, expected_public_input)
+}
+
+fn check_public_input(inputs: &[Fr], expected_public_input: B256) -> Result<(), Box<dyn Error>> {
+    let public_input = inputs.first().ok_or("Snark proof has no public inputs")?;
+    let mut bytes = vec![];
+    public_input.into_repr().write_be(&mut bytes)?;
+    let public_input = B256::from_slice(&bytes);
+    if public_input != expected_public_input {
+        return Err(format!(
+            "Snark public input mismatch: proof has {}, but the batches need {}",
+            public_input, expected_public_input
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Checks the snark before it is sent: its public input must be the expected one, and (if the verification key
+/// is given) it must pass the PLONK verifier.
+pub fn verify_snark(
+    path: &str,
+    vk_path: Option<&str>,
+    expected_public_input: B256,
+) -> Result<(), Box<dyn Error>> {
+    let proof: PlonkProof<Bn256, ZkSyncSnarkWrapperCircuit> = load_proof(path)?;
+    check_public_input(&proof.inputs, expected_public_input)?;
+
+    if let Some(vk_path) = vk_path {
+        let vk: VerificationKey<Bn256, ZkSyncSnarkWrapperCircuit> =
+            serde_json::from_str(&fs::read_to_string(vk_path)?)?;
+        let valid = verify::<Bn256, ZkSyncSnarkWrapperCircuit, RollingKeccakTranscript<Fr>>(
+            &vk, &proof, None,
+        )?;
+        if !valid {
+            return Err(format!("Snark proof doesn't verify with the key from {}", vk_path).into());
+        }
+        eprintln!("Snark proof verified locally with the key from {}", vk_path);
+    }
+    Ok(())
+}
+
+/// Hash of the verification key in a given file - the same as `verificationKeyHash()` of the L1 verifier.
+pub fn snark_vk_hash(vk_path: &str) -> Result<B256, Box<dyn Error>> {
+    let vk: VerificationKey<Bn256, ZkSyncSnarkWrapperCircuit> =
+        serde_json::from_str(&fs::read_to_string(vk_path)?)?;
+    common::snark_vk_hash(&vk)
+}
+
+/// Hash of the FFLONK verification key in a given file - the same as `verificationKeyHash()` of the L1 verifier.
+pub fn fflonk_vk_hash(vk_path: &str) -> Result<B256, Box<dyn Error>> {
+    let vk: FflonkSnarkVerifierCircuitVK = serde_json::from_str(&fs::read_to_string(vk_path)?)?;
+    common::fflonk_vk_hash(&vk)
+}
+
+/// Reads the proof - either as JSON, or as base64-encoded bincode in the 'proof' field.
+fn load_proof<T: DeserializeOwned>(path: &str) -> Result<T, Box<dyn Error>> {
     // Load the JSON file from disk.
     let file_content = fs::read_to_string(path)?;
     let json_value: Value = serde_json::from_str(&file_content)?;
@@ -23,16 +108,10 @@ pub fn load_snark_from_file(path: &str) -> Result<Vec<String>, Box<dyn Error>> {
         let decoded_bytes = base64::engine::general_purpose::STANDARD.decode(encoded)?;
 
         // Parse the decoded string as JSON.
-        let inner_value: PlonkProof<Bn256, ZkSyncSnarkWrapperCircuit> =
-            bincode::deserialize(&decoded_bytes)?;
-        inner_value
+        bincode::deserialize(&decoded_bytes)?
     } else {
-        let inner_value: PlonkProof<Bn256, ZkSyncSnarkWrapperCircuit> =
-            serde_json::from_str(&file_content)?;
-        inner_value
+        serde_json::from_str(&file_content)?
     };
 
-    let (_, serialized_proof) = crypto_codegen::serialize_proof(&inner_value);
-
-    Ok(serialized_proof.iter().map(|x| x.to_string()).collect())
+    Ok(inner_value)
 }
@@ -0,0 +1,188 @@
+// Long-running mode: fake-proves and executes the new batches as they get committed.
+
+use std::{collections::HashMap, error::Error, time::Duration};
+
+use alloy::providers::{Provider, WalletProvider};
+use tokio::sync::watch;
+
+use common::{
+    IHyperchain::IHyperchainInstance, StoredBatchInfo, snark_public_input_for_range,
+    sync_batch_index,
+};
+
+use crate::{
+    execute::execute_batches,
+    fees::FeePolicy,
+    output::{OutputFormat, TxResult},
+    priority_ops::PriorityOps,
+    prove::fake_prove_batches,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BatchCounts {
+    committed: u64,
+    verified: u64,
+    executed: u64,
+}
+
+impl BatchCounts {
+    async fn fetch<P: Provider>(contract: &IHyperchainInstance<P>) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            committed: contract
+                .getTotalBatchesCommitted()
+                .call()
+                .await?
+                .try_into()?,
+            verified: contract
+                .getTotalBatchesVerified()
+                .call()
+                .await?
+                .try_into()?,
+            executed: contract
+                .getTotalBatchesExecuted()
+                .call()
+                .await?
+                .try_into()?,
+        })
+    }
+}
+
+/// Polls the contract until SIGINT. On errors, waits exponentially longer (up to `max_backoff`) before retrying.
+pub async fn watch_batches<P: Provider + WalletProvider + Clone>(
+    contract: IHyperchainInstance<P>,
+    index_dir: &str,
+    priority_ops: &PriorityOps,
+    fees: &FeePolicy,
+    output: OutputFormat,
+    poll_interval: Duration,
+    max_backoff: Duration,
+) {
+    let (shutdown_sender, mut shutdown) = watch::channel(false);
+    tokio::spawn(async move {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            eprintln!("Failed to listen for SIGINT: {}", err);
+            // Keep the sender alive, so that the main loop doesn't treat it as a shutdown.
+            std::future::pending::<()>().await;
+        }
+        eprintln!("Received SIGINT - stopping after the current step (press again to stop now)");
+        let _ = shutdown_sender.send(true);
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+
+    let mut last_counts = None;
+    let mut backoff = poll_interval;
+    while !*shutdown.borrow() {
+        let delay = match step(
+            &contract,
+            index_dir,
+            priority_ops,
+            fees,
+            output,
+            &mut last_counts,
+        )
+        .await
+        {
+            Ok(()) => {
+                backoff = poll_interval;
+                poll_interval
+            }
+            Err(err) => {
+                eprintln!("Error: {} - retrying in {}s", err, backoff.as_secs());
+                let delay = backoff;
+                backoff = (backoff * 2).min(max_backoff);
+                delay
+            }
+        };
+        tokio::select! {
+            _ = shutdown.changed() => {}
+            _ = tokio::time::sleep(delay) => {}
+        }
+    }
+    eprintln!("Stopped watching");
+}
+
+/// Proves all the committed batches, and executes all the proven ones.
+async fn step<P: Provider + WalletProvider + Clone>(
+    contract: &IHyperchainInstance<P>,
+    index_dir: &str,
+    priority_ops: &PriorityOps,
+    fees: &FeePolicy,
+    output: OutputFormat,
+    last_counts: &mut Option<BatchCounts>,
+) -> Result<(), Box<dyn Error>> {
+    // Fetch the counts before syncing the index, so that the index has all the committed batches.
+    let counts = BatchCounts::fetch(contract).await?;
+    if *last_counts != Some(counts) {
+        eprintln!(
+            "Batches committed: {}, verified: {}, executed: {}",
+            counts.committed, counts.verified, counts.executed
+        );
+        *last_counts = Some(counts);
+    }
+    if counts.verified == counts.committed && counts.executed == counts.verified {
+        return Ok(());
+    }
+
+    let index = sync_batch_index(contract.provider(), *contract.address(), index_dir).await?;
+    let stored: HashMap<u64, StoredBatchInfo> = index.stored.into_iter().collect();
+
+    if counts.verified < counts.committed {
+        eprintln!(
+            "Fake proving from {} to {}",
+            counts.verified + 1,
+            counts.committed
+        );
+        let public_input =
+            snark_public_input_for_range(&stored, counts.verified + 1, counts.committed)?;
+        let result = fake_prove_batches(
+            contract.clone(),
+            counts.verified + 1,
+            counts.committed,
+            &stored,
+            public_input,
+            fees,
+            false,
+        )
+        .await?;
+        report(&result, output)?;
+    }
+
+    let total_verified: u64 = contract
+        .getTotalBatchesVerified()
+        .call()
+        .await?
+        .try_into()?;
+    if counts.executed < total_verified {
+        eprintln!(
+            "Executing from {} to {}",
+            counts.executed + 1,
+            total_verified
+        );
+        let result = execute_batches(
+            contract.clone(),
+            counts.executed + 1,
+            total_verified,
+            priority_ops,
+            &stored,
+            fees,
+            false,
+        )
+        .await?;
+        report(&result, output)?;
+    }
+    Ok(())
+}
+
+/// Prints the result (in JSON mode - one line per transaction), and turns the failed transaction into an error.
+fn report(result: &TxResult, output: OutputFormat) -> Result<(), Box<dyn Error>> {
+    match output {
+        OutputFormat::Text => result.print(output),
+        OutputFormat::Json => println!("{}", serde_json::to_string(result)?),
+    }
+    if result.is_failed() {
+        return Err(format!("{} transaction failed", result.action).into());
+    }
+    Ok(())
+}
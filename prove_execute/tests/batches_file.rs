@@ -0,0 +1,48 @@
+// Batches exported to a file, and loaded back (without L1).
+
+use std::collections::HashMap;
+
+use alloy::primitives::{Address, B256, U256};
+use common::StoredBatchInfo;
+use prove_execute::batches_file::BatchesFile;
+
+const DIAMOND_PROXY: Address = Address::repeat_byte(0xdd);
+
+fn stored(batch_number: u64) -> StoredBatchInfo {
+    StoredBatchInfo {
+        batchNumber: batch_number,
+        batchHash: B256::repeat_byte(batch_number as u8),
+        indexRepeatedStorageChanges: 0,
+        numberOfLayer1Txs: U256::ZERO,
+        priorityOperationsHash: B256::ZERO,
+        l2LogsTreeRoot: B256::ZERO,
+        timestamp: U256::ZERO,
+        commitment: B256::repeat_byte(0x10 + batch_number as u8),
+    }
+}
+
+#[test]
+fn save_and_load() {
+    let path = std::env::temp_dir().join(format!("batches_file_{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    let stored: HashMap<u64, StoredBatchInfo> = (0..3).map(|n| (n, stored(n))).collect();
+    BatchesFile::new(1, DIAMOND_PROXY, &HashMap::new(), &stored)
+        .save(path)
+        .unwrap();
+
+    // The L1 chain id is checked only when it is given.
+    let (commits, loaded) = BatchesFile::load(path, Some(1), DIAMOND_PROXY).unwrap();
+    assert!(commits.is_empty());
+    assert_eq!(loaded, stored);
+    assert_eq!(
+        BatchesFile::load(path, None, DIAMOND_PROXY).unwrap().1,
+        stored
+    );
+
+    let err = BatchesFile::load(path, Some(11155111), DIAMOND_PROXY).unwrap_err();
+    assert!(err.to_string().contains("is for L1 chain 1"), "{}", err);
+    let err = BatchesFile::load(path, None, Address::ZERO).unwrap_err();
+    assert!(err.to_string().contains("is for diamond proxy"), "{}", err);
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,76 @@
+// Fees of the replacement transactions.
+
+use prove_execute::fees::{FeeMode, FeePolicy, Fees};
+
+fn policy(fee_mode: FeeMode, max_fee: Option<u128>, max_priority_fee: Option<u128>) -> FeePolicy {
+    FeePolicy {
+        fee_mode,
+        max_fee_per_gas: max_fee,
+        max_priority_fee_per_gas: max_priority_fee,
+        fee_multiplier: 1.5,
+        tx_timeout: 180,
+        fee_bump_percent: 20,
+        max_replacements: 5,
+    }
+}
+
+fn fees(max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> Fees {
+    Fees {
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+    }
+}
+
+#[test]
+fn bump() {
+    let estimate = policy(FeeMode::Estimate, None, None);
+    // 20% more, plus 1 wei.
+    assert_eq!(
+        estimate.bumped_fees(fees(100_000_000_000, 2_000_000_000)),
+        Some(fees(120_000_000_001, 2_400_000_001))
+    );
+    // Rounded down, before the extra wei is added.
+    assert_eq!(estimate.bumped_fees(fees(99, 9)), Some(fees(119, 11)));
+}
+
+#[test]
+fn bump_minimum_increment() {
+    // Too small for the percentage to make any difference - the replacement is still 1 wei more expensive.
+    let estimate = policy(FeeMode::Estimate, None, None);
+    assert_eq!(estimate.bumped_fees(fees(0, 0)), Some(fees(1, 1)));
+    assert_eq!(estimate.bumped_fees(fees(4, 1)), Some(fees(5, 2)));
+
+    let no_percent = FeePolicy {
+        fee_bump_percent: 0,
+        ..estimate
+    };
+    assert_eq!(
+        no_percent.bumped_fees(fees(1000, 100)),
+        Some(fees(1001, 101))
+    );
+}
+
+#[test]
+fn bump_capped() {
+    let capped = policy(FeeMode::Capped, Some(1000), Some(150));
+    assert_eq!(capped.bumped_fees(fees(500, 100)), Some(fees(601, 121)));
+    // Only up to the cap.
+    assert_eq!(capped.bumped_fees(fees(900, 140)), Some(fees(1000, 150)));
+    // Once any of the fees is at its cap, the replacement would not be strictly more expensive.
+    assert_eq!(capped.bumped_fees(fees(1000, 100)), None);
+    assert_eq!(capped.bumped_fees(fees(500, 150)), None);
+
+    // Priority fee is never above the max fee.
+    let no_priority_cap = policy(FeeMode::Capped, Some(1000), None);
+    assert_eq!(
+        no_priority_cap.bumped_fees(fees(900, 900)),
+        Some(fees(1000, 1000))
+    );
+}
+
+#[test]
+fn bump_fixed() {
+    // Fixed fees are never bumped above the given values.
+    let fixed = policy(FeeMode::Fixed, Some(1000), Some(100));
+    assert_eq!(fixed.bumped_fees(fees(1000, 100)), None);
+}
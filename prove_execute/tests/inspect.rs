@@ -0,0 +1,247 @@
+// Decoding of the commit / prove / execute calls - each one encoded here, and decoded back.
+
+use alloy::{
+    primitives::{Address, B256, Bytes, U256},
+    sol_types::SolCall,
+};
+use common::{
+    CommitBoojumOSBatchInfo, DecodedCommitData, IHyperchain, ProtocolVersion, StoredBatchInfo,
+    commit_tx::IValidatorTimelock,
+};
+use prove_execute::inspect::{InspectedCall, Target, decode_call};
+
+const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion {
+    major: 0,
+    minor: 28,
+    patch: 0,
+};
+const CHAIN_ADDRESS: Address = Address::repeat_byte(0xcc);
+
+fn stored(batch_number: u64) -> StoredBatchInfo {
+    StoredBatchInfo {
+        batchNumber: batch_number,
+        batchHash: B256::repeat_byte(batch_number as u8),
+        indexRepeatedStorageChanges: 0,
+        numberOfLayer1Txs: U256::from(1),
+        priorityOperationsHash: B256::repeat_byte(0x22),
+        l2LogsTreeRoot: B256::repeat_byte(0x33),
+        timestamp: U256::ZERO,
+        commitment: B256::repeat_byte(0x99),
+    }
+}
+
+fn commit(batch_number: u64) -> CommitBoojumOSBatchInfo {
+    CommitBoojumOSBatchInfo {
+        batchNumber: batch_number,
+        newStateCommitment: B256::repeat_byte(0x11),
+        numberOfLayer1Txs: U256::from(1),
+        priorityOperationsHash: B256::repeat_byte(0x22),
+        l2LogsTreeRoot: B256::repeat_byte(0x33),
+        l2DaValidator: Address::repeat_byte(0x44),
+        daCommitment: B256::repeat_byte(0x55),
+        firstBlockTimestamp: 1_700_000_000,
+        lastBlockTimestamp: 1_700_000_100,
+        chainId: U256::from(270),
+        operatorDAInput: Bytes::from_static(&[0xde, 0xad]),
+    }
+}
+
+/// Version byte, followed by the ABI encoded payload (without the selector).
+fn payload(call: impl SolCall) -> Bytes {
+    let mut data = vec![0u8];
+    call.abi_encode_raw(&mut data);
+    data.into()
+}
+
+fn commit_data() -> Bytes {
+    payload(IHyperchain::commitDataPiecesCall {
+        stored: stored(4),
+        commits: vec![commit(5), commit(6)],
+    })
+}
+
+fn proof_data() -> Bytes {
+    payload(IHyperchain::proofPayloadCall {
+        old: stored(4),
+        newInfo: vec![stored(5), stored(6)],
+        proof: vec![U256::from(3), U256::ZERO, U256::from(13), U256::from(42)],
+    })
+}
+
+fn execute_data() -> Bytes {
+    payload(IHyperchain::executePayloadCall {
+        executeData: vec![stored(5)],
+        priorityOps: vec![IHyperchain::PriorityOpsBatchInfo {
+            leftPath: vec![B256::repeat_byte(0x01)],
+            rightPath: vec![B256::repeat_byte(0x02)],
+            itemHashes: vec![B256::repeat_byte(0x03)],
+        }],
+    })
+}
+
+fn check_commit(call: InspectedCall, expected_target: Target) {
+    let InspectedCall::Commit {
+        target,
+        process_from,
+        process_to,
+        encoding_version,
+        commit_data,
+    } = call
+    else {
+        panic!("Not a commit: {:?}", call);
+    };
+    assert_eq!(format!("{}", target), format!("{}", expected_target));
+    assert_eq!((process_from, process_to), (U256::from(5), U256::from(6)));
+    assert_eq!(encoding_version, 0);
+    assert_eq!(
+        commit_data,
+        DecodedCommitData::BoojumOs {
+            stored: stored(4),
+            commits: vec![commit(5), commit(6)],
+        }
+    );
+}
+
+#[test]
+fn commit_round_trip() {
+    let input = IHyperchain::commitBatchesSharedBridgeCall {
+        _0: U256::from(270),
+        _processFrom: U256::from(5),
+        _processTo: U256::from(6),
+        commitData: commit_data(),
+    }
+    .abi_encode();
+    check_commit(
+        decode_call(&input.into(), PROTOCOL_VERSION).unwrap(),
+        Target::ChainId(U256::from(270)),
+    );
+
+    let input = IValidatorTimelock::commitBatchesSharedBridgeCall {
+        _chainAddress: CHAIN_ADDRESS,
+        _processFrom: U256::from(5),
+        _processTo: U256::from(6),
+        _commitData: commit_data(),
+    }
+    .abi_encode();
+    check_commit(
+        decode_call(&input.into(), PROTOCOL_VERSION).unwrap(),
+        Target::ChainAddress(CHAIN_ADDRESS),
+    );
+}
+
+fn check_prove(call: InspectedCall, expected_target: Target) {
+    let InspectedCall::Prove {
+        target,
+        process_from,
+        process_to,
+        encoding_version,
+        old,
+        new_info,
+        proof,
+    } = call
+    else {
+        panic!("Not a prove: {:?}", call);
+    };
+    assert_eq!(format!("{}", target), format!("{}", expected_target));
+    assert_eq!((process_from, process_to), (U256::from(5), U256::from(6)));
+    assert_eq!(encoding_version, 0);
+    assert_eq!(old, stored(4));
+    assert_eq!(new_info, [stored(5), stored(6)]);
+    assert_eq!(
+        proof,
+        [U256::from(3), U256::ZERO, U256::from(13), U256::from(42)]
+    );
+}
+
+#[test]
+fn prove_round_trip() {
+    let input = IHyperchain::proveBatchesSharedBridgeCall {
+        _0: U256::from(270),
+        _processBatchFrom: U256::from(5),
+        _processBatchTo: U256::from(6),
+        _proofData: proof_data(),
+    }
+    .abi_encode();
+    check_prove(
+        decode_call(&input.into(), PROTOCOL_VERSION).unwrap(),
+        Target::ChainId(U256::from(270)),
+    );
+
+    let input = IValidatorTimelock::proveBatchesSharedBridgeCall {
+        _chainAddress: CHAIN_ADDRESS,
+        _processBatchFrom: U256::from(5),
+        _processBatchTo: U256::from(6),
+        _proofData: proof_data(),
+    }
+    .abi_encode();
+    check_prove(
+        decode_call(&input.into(), PROTOCOL_VERSION).unwrap(),
+        Target::ChainAddress(CHAIN_ADDRESS),
+    );
+}
+
+fn check_execute(call: InspectedCall, expected_target: Target) {
+    let InspectedCall::Execute {
+        target,
+        process_from,
+        process_to,
+        encoding_version,
+        execute_data,
+        priority_ops,
+    } = call
+    else {
+        panic!("Not an execute: {:?}", call);
+    };
+    assert_eq!(format!("{}", target), format!("{}", expected_target));
+    assert_eq!((process_from, process_to), (U256::from(5), U256::from(5)));
+    assert_eq!(encoding_version, 0);
+    assert_eq!(execute_data, [stored(5)]);
+    assert_eq!(
+        priority_ops,
+        [IHyperchain::PriorityOpsBatchInfo {
+            leftPath: vec![B256::repeat_byte(0x01)],
+            rightPath: vec![B256::repeat_byte(0x02)],
+            itemHashes: vec![B256::repeat_byte(0x03)],
+        }]
+    );
+}
+
+#[test]
+fn execute_round_trip() {
+    let input = IHyperchain::executeBatchesSharedBridgeCall {
+        _0: U256::from(270),
+        _processFrom: U256::from(5),
+        _processTo: U256::from(5),
+        _executeData: execute_data(),
+    }
+    .abi_encode();
+    check_execute(
+        decode_call(&input.into(), PROTOCOL_VERSION).unwrap(),
+        Target::ChainId(U256::from(270)),
+    );
+
+    let input = IValidatorTimelock::executeBatchesSharedBridgeCall {
+        _chainAddress: CHAIN_ADDRESS,
+        _processFrom: U256::from(5),
+        _processTo: U256::from(5),
+        _executeData: execute_data(),
+    }
+    .abi_encode();
+    check_execute(
+        decode_call(&input.into(), PROTOCOL_VERSION).unwrap(),
+        Target::ChainAddress(CHAIN_ADDRESS),
+    );
+}
+
+#[test]
+fn unknown_calls() {
+    let input = IHyperchain::revertBatchesSharedBridgeCall {
+        _chainId: U256::from(270),
+        _newLastBatch: U256::from(4),
+    }
+    .abi_encode();
+    let err = decode_call(&input.into(), PROTOCOL_VERSION).unwrap_err();
+    assert!(err.to_string().contains("Unknown selector"), "{}", err);
+
+    assert!(decode_call(&Bytes::from_static(&[0x01, 0x02]), PROTOCOL_VERSION).is_err());
+}
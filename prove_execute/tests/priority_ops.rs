@@ -0,0 +1,245 @@
+// Priority operations from a mocked sequencer and a mocked L1 (the responses are returned in the order of the requests).
+
+use std::collections::HashMap;
+
+use alloy::{
+    primitives::{Address, B256, Bytes, U256},
+    providers::{ProviderBuilder, mock::Asserter},
+    rpc::{client::RpcClient, types::Log},
+    sol_types::{SolEvent, SolValue},
+};
+use common::{IHyperchain, L2CanonicalTransaction, StoredBatchInfo};
+use prove_execute::{
+    priority_ops::{
+        PriorityOpsCache, PriorityRequests, cross_check, priority_ops_from_l1,
+        priority_ops_from_sequencer,
+    },
+    sequencer::Sequencer,
+};
+use serde_json::json;
+
+const DIAMOND_PROXY: Address = Address::repeat_byte(0xdd);
+
+/// Single batch request at a time - so that the mocked responses are used in order.
+fn sequencer(asserter: &Asserter, batch_size: usize) -> Sequencer {
+    Sequencer::with_client(RpcClient::mocked(asserter.clone()), batch_size, 1)
+}
+
+fn block(txs: &[(&str, B256)]) -> serde_json::Value {
+    let transactions: Vec<_> = txs
+        .iter()
+        .map(|(tx_type, hash)| json!({"type": tx_type, "hash": hash}))
+        .collect();
+    json!({ "transactions": transactions })
+}
+
+fn stored(batch_number: u64, l1_txs: u64) -> StoredBatchInfo {
+    StoredBatchInfo {
+        batchNumber: batch_number,
+        batchHash: B256::ZERO,
+        indexRepeatedStorageChanges: 0,
+        numberOfLayer1Txs: U256::from(l1_txs),
+        priorityOperationsHash: B256::ZERO,
+        l2LogsTreeRoot: B256::ZERO,
+        timestamp: U256::ZERO,
+        commitment: B256::ZERO,
+    }
+}
+
+fn priority_request_log(tx_id: u64, tx_hash: B256, block_number: u64) -> Log {
+    let event = IHyperchain::NewPriorityRequest {
+        txId: U256::from(tx_id),
+        txHash: tx_hash,
+        expirationTimestamp: 0,
+        transaction: L2CanonicalTransaction {
+            txType: U256::from(0xff),
+            from: U256::ZERO,
+            to: U256::ZERO,
+            gasLimit: U256::ZERO,
+            gasPerPubdataByteLimit: U256::ZERO,
+            maxFeePerGas: U256::ZERO,
+            maxPriorityFeePerGas: U256::ZERO,
+            paymaster: U256::ZERO,
+            nonce: U256::from(tx_id),
+            value: U256::ZERO,
+            reserved: [U256::ZERO; 4],
+            data: Bytes::new(),
+            signature: Bytes::new(),
+            factoryDeps: vec![],
+            paymasterInput: Bytes::new(),
+            reservedDynamic: Bytes::new(),
+        },
+        factoryDeps: vec![],
+    };
+    Log {
+        inner: alloy::primitives::Log {
+            address: DIAMOND_PROXY,
+            data: event.encode_log_data(),
+        },
+        block_number: Some(block_number),
+        log_index: Some(tx_id),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn sequencer_batch_block_ranges() {
+    let asserter = Asserter::new();
+    // Two batch requests (of 2 and 1 calls).
+    asserter.push_success(&["0x1", "0x2"]);
+    asserter.push_success(&["0x3", "0x5"]);
+    asserter.push_success(&["0x6", "0x6"]);
+    let ranges = sequencer(&asserter, 2)
+        .batch_block_ranges(&[1, 2, 3])
+        .await
+        .unwrap();
+    assert_eq!(ranges, [Some((1, 2)), Some((3, 5)), Some((6, 6))]);
+
+    // The sequencer doesn't know the method - that's not an error.
+    asserter.push_failure(
+        serde_json::from_value(json!({"code": -32601, "message": "Method not found"})).unwrap(),
+    );
+    let ranges = sequencer(&asserter, 2)
+        .batch_block_ranges(&[1])
+        .await
+        .unwrap();
+    assert_eq!(ranges, [None]);
+
+    // Any other error is.
+    asserter.push_failure_msg("internal error");
+    let err = sequencer(&asserter, 2)
+        .batch_block_ranges(&[1])
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("batch 1"), "{}", err);
+}
+
+#[tokio::test]
+async fn sequencer_malformed_responses() {
+    let asserter = Asserter::new();
+    asserter.push_success(&"0x1");
+    let err = sequencer(&asserter, 10)
+        .batch_block_ranges(&[7])
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Batch 7 not found on the sequencer"),
+        "{}",
+        err
+    );
+
+    asserter.push_success(&[1, 2]);
+    let err = sequencer(&asserter, 10)
+        .batch_block_ranges(&[7])
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not a string"), "{}", err);
+
+    // Block without the transactions.
+    asserter.push_success(&json!(null));
+    let err = sequencer(&asserter, 10)
+        .priority_txs(&[5])
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Block 5 not found"), "{}", err);
+
+    asserter.push_success(&block(&[("0x2a", B256::ZERO)]));
+    asserter.push_success(&json!({"transactions": [{"type": "0x2a", "hash": "0x12"}]}));
+    assert!(
+        sequencer(&asserter, 10)
+            .priority_txs(&[5, 6])
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn sequencer_priority_txs() {
+    let asserter = Asserter::new();
+    asserter.push_success(&block(&[
+        ("0x2a", B256::repeat_byte(0x01)),
+        ("0x2", B256::repeat_byte(0x02)),
+        ("0x2a", B256::repeat_byte(0x03)),
+    ]));
+    asserter.push_success(&block(&[]));
+    let txs = sequencer(&asserter, 10)
+        .priority_txs(&[5, 6])
+        .await
+        .unwrap();
+    assert_eq!(
+        txs,
+        [
+            vec![B256::repeat_byte(0x01), B256::repeat_byte(0x03)],
+            vec![]
+        ]
+    );
+}
+
+/// Batch 1 with two priority operations - on L1 and on the sequencer (where the second one is `sequencer_second`).
+async fn l1_and_sequencer_ops(
+    sequencer_second: B256,
+) -> (HashMap<u64, Vec<B256>>, HashMap<u64, Vec<B256>>) {
+    let stored = HashMap::from([(0, stored(0, 0)), (1, stored(1, 2))]);
+
+    let l1 = Asserter::new();
+    let provider = ProviderBuilder::new().connect_mocked_client(l1.clone());
+    let contract = IHyperchain::new(DIAMOND_PROXY, provider);
+    // getTotalBatchesExecuted, getFirstUnprocessedPriorityTx, getPriorityTreeStartIndex
+    for value in [0, 0, 0] {
+        l1.push_success(&Bytes::from(U256::from(value).abi_encode()));
+    }
+    // Latest block, and a single chunk of logs.
+    l1.push_success(&U256::from(100));
+    l1.push_success(&vec![
+        priority_request_log(0, B256::repeat_byte(0x01), 10),
+        priority_request_log(1, B256::repeat_byte(0x02), 20),
+    ]);
+    let l1_txs = priority_ops_from_l1(&contract, 1, 1, &stored, &mut PriorityRequests::default())
+        .await
+        .unwrap();
+
+    let asserter = Asserter::new();
+    asserter.push_success(&["0x1", "0x2"]);
+    asserter.push_success(&block(&[("0x2a", B256::repeat_byte(0x01))]));
+    asserter.push_success(&block(&[("0x2a", sequencer_second)]));
+    let index_dir = std::env::temp_dir().join(format!(
+        "priority_ops_{}_{}",
+        std::process::id(),
+        sequencer_second
+    ));
+    let mut cache = PriorityOpsCache::new(1, DIAMOND_PROXY);
+    let sequencer_txs = priority_ops_from_sequencer(
+        &sequencer(&asserter, 10),
+        &mut cache,
+        index_dir.to_str().unwrap(),
+        1,
+    )
+    .await
+    .unwrap();
+    std::fs::remove_dir_all(&index_dir).unwrap();
+
+    (l1_txs, sequencer_txs)
+}
+
+#[tokio::test]
+async fn cross_check_match() {
+    let (l1_txs, sequencer_txs) = l1_and_sequencer_ops(B256::repeat_byte(0x02)).await;
+    assert_eq!(
+        l1_txs[&1],
+        [B256::repeat_byte(0x01), B256::repeat_byte(0x02)]
+    );
+    cross_check(&l1_txs, &sequencer_txs, 1, 1).unwrap();
+}
+
+#[tokio::test]
+async fn cross_check_mismatch() {
+    let (l1_txs, sequencer_txs) = l1_and_sequencer_ops(B256::repeat_byte(0x03)).await;
+    let err = cross_check(&l1_txs, &sequencer_txs, 1, 1).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Priority operations of batch 1 differ"),
+        "{}",
+        err
+    );
+}
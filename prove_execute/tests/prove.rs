@@ -0,0 +1,16 @@
+// Proof types and the verifiers of DualVerifier that check them.
+
+use prove_execute::prove::ProofType;
+
+#[test]
+fn verifier_types() {
+    // First element of the proof array.
+    assert_eq!(ProofType::Fflonk.verifier_type(), 0);
+    assert_eq!(ProofType::Plonk.verifier_type(), 1);
+    assert_eq!(ProofType::Ohbender.verifier_type(), 2);
+
+    // `verificationKeyHash` reverts for anything but FFLONK (0) and PLONK (1).
+    assert_eq!(ProofType::Fflonk.vk_verifier_type(), 0);
+    assert_eq!(ProofType::Plonk.vk_verifier_type(), 1);
+    assert_eq!(ProofType::Ohbender.vk_verifier_type(), 1);
+}